@@ -0,0 +1,267 @@
+//! Annotated listing of sierra programs, showing how many times each statement was executed
+//! in the trace and how much it cost, similarly to `perf annotate`.
+use crate::disassembly::function_statements;
+use crate::profiler_config::FunctionLevelConfig;
+use crate::sierra_loader::CompiledArtifactsCache;
+use crate::trace_reader::collect_calls_with_cairo_execution_info;
+use crate::trace_reader::function_trace_builder::collect_function_level_profiling_info;
+use crate::trace_reader::function_trace_builder::cost::BuiltinCounts;
+use crate::versioned_constants_reader::{BuiltinGasCosts, VersionedConstants};
+use anyhow::Result;
+use cairo_annotations::trace_data::CallTraceV1;
+use cairo_lang_sierra::program::{BranchTarget, GenStatement, Program, StatementIdx};
+use cairo_lang_sierra_to_casm::compiler::CairoProgramDebugInfo;
+use camino::Utf8PathBuf;
+use prettytable::{Table, format};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::ops::AddAssign;
+
+/// Costs of a sierra statement, summed over all its executions in the trace.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StatementCosts {
+    pub executions: usize,
+    pub steps: usize,
+    /// Builtins used by the statement. Known only for programs compiled with gas enabled.
+    pub builtins: BuiltinCounts,
+}
+
+impl StatementCosts {
+    /// Sierra gas consumed by the statement, following the profile: 100 per step plus the cost of
+    /// builtins used by the statement.
+    pub fn sierra_gas(&self, builtin_gas_costs: &BuiltinGasCosts) -> usize {
+        100 * self.steps + self.builtins.sierra_gas(builtin_gas_costs)
+    }
+}
+
+impl AddAssign for StatementCosts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.executions += rhs.executions;
+        self.steps += rhs.steps;
+        self.builtins += rhs.builtins;
+    }
+}
+
+/// Returns costs of statements of programs executed in the trace, keyed by absolute paths of
+/// the programs. They are collected by function level profiling of calls of the trace.
+pub fn collect_statement_costs(
+    trace: &CallTraceV1,
+    compiled_artifacts_cache: &CompiledArtifactsCache,
+    cairo_enable_gas: bool,
+    sierra_gas_tracking: bool,
+) -> Result<BTreeMap<Utf8PathBuf, HashMap<StatementIdx, StatementCosts>>> {
+    let function_level_config = FunctionLevelConfig::statement_costs_only();
+    let mut calls = vec![];
+    collect_calls_with_cairo_execution_info(trace, &mut calls);
+
+    let mut statement_costs: BTreeMap<Utf8PathBuf, HashMap<StatementIdx, StatementCosts>> =
+        BTreeMap::new();
+    for call in calls {
+        let cairo_execution_info = call
+            .cairo_execution_info
+            .as_ref()
+            .expect("Only calls with Cairo execution info are collected");
+        let absolute_source_sierra_path = compiled_artifacts_cache
+            .resolve_sierra_path(&cairo_execution_info.source_sierra_path)?;
+
+        let function_level_profiling_info = collect_function_level_profiling_info(
+            compiled_artifacts_cache.get_compiled_artifacts_for_path(&absolute_source_sierra_path),
+            &cairo_execution_info.casm_level_info,
+            &function_level_config,
+            sierra_gas_tracking,
+            vec![],
+            false,
+            &mut VecDeque::new(),
+            &mut VecDeque::new(),
+            cairo_enable_gas,
+        );
+        let program_costs = statement_costs
+            .entry(absolute_source_sierra_path)
+            .or_default();
+        for (statement_idx, costs) in function_level_profiling_info.statement_costs {
+            *program_costs.entry(statement_idx).or_default() += costs;
+        }
+    }
+
+    Ok(statement_costs)
+}
+
+/// Returns the annotated listing of the function (or whole programs if `function_name` is `None`)
+/// in every program in which it was executed in the trace, or `None` if there are no such programs.
+/// Sierra gas is shown only if `sierra_gas_tracking` is set.
+pub fn annotate(
+    trace: &CallTraceV1,
+    compiled_artifacts_cache: &CompiledArtifactsCache,
+    versioned_constants: &VersionedConstants,
+    function_name: Option<&str>,
+    cairo_enable_gas: bool,
+    sierra_gas_tracking: bool,
+) -> Result<Option<String>> {
+    let builtin_gas_costs = &versioned_constants.os_constants.builtin_gas_costs;
+    let mut annotation = String::new();
+
+    for (sierra_path, statement_costs) in collect_statement_costs(
+        trace,
+        compiled_artifacts_cache,
+        cairo_enable_gas,
+        sierra_gas_tracking,
+    )? {
+        let compiled_artifacts =
+            compiled_artifacts_cache.get_compiled_artifacts_for_path(&sierra_path);
+        let program = &compiled_artifacts.sierra_program;
+        let statements = match function_name {
+            Some(function_name) => {
+                let Some(statements) = function_statements(program, function_name) else {
+                    continue;
+                };
+                if !statements
+                    .clone()
+                    .any(|idx| statement_costs.contains_key(&StatementIdx(idx)))
+                {
+                    continue;
+                }
+                statements
+            }
+            None => 0..program.statements.len(),
+        };
+        let executions_without_casm = executions_of_statements_without_casm(
+            program,
+            &compiled_artifacts.casm_debug_info,
+            &statement_costs,
+        );
+        let mut unknown_executions = false;
+        let function_names: HashMap<usize, String> = program
+            .funcs
+            .iter()
+            .map(|function| (function.entry_point.0, function.id.to_string()))
+            .collect();
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        if sierra_gas_tracking {
+            table.set_titles(row![r->"executions", r->"steps", r->"sierra gas", "statement"]);
+        } else {
+            table.set_titles(row![r->"executions", r->"steps", "statement"]);
+        }
+
+        for idx in statements {
+            if let Some(function_name) = function_names.get(&idx) {
+                let mut row = row![r->"", r->""];
+                if sierra_gas_tracking {
+                    row.add_cell(cell!(r->""));
+                }
+                row.add_cell(cell!(l->format!("{function_name}:")));
+                table.add_row(row);
+            }
+
+            // Statements which were not executed are left blank, so the hot ones stand out
+            let statement_executions_without_casm = executions_without_casm.get(&idx);
+            let unknown = statement_executions_without_casm == Some(&None);
+            unknown_executions |= unknown;
+            let costs = match statement_executions_without_casm {
+                Some(Some(executions)) if *executions > 0 => Some(StatementCosts {
+                    executions: *executions,
+                    ..StatementCosts::default()
+                }),
+                _ => statement_costs.get(&StatementIdx(idx)).copied(),
+            };
+            let cost_cell = |cost: &dyn Fn(&StatementCosts) -> usize| {
+                if unknown {
+                    return "-".to_string();
+                }
+                costs
+                    .as_ref()
+                    .map(|costs| cost(costs).to_string())
+                    .unwrap_or_default()
+            };
+            let mut row = row![
+                r->cost_cell(&|costs| costs.executions),
+                r->cost_cell(&|costs| costs.steps)
+            ];
+            if sierra_gas_tracking {
+                row.add_cell(cell!(r->cost_cell(&|costs| costs.sierra_gas(builtin_gas_costs))));
+            }
+            row.add_cell(cell!(l->format!("    {idx}: {}", program.statements[idx])));
+            table.add_row(row);
+        }
+
+        match function_name {
+            Some(function_name) => writeln!(
+                annotation,
+                "\nAnnotation of {function_name} from {sierra_path}\n"
+            )?,
+            None => writeln!(annotation, "\nAnnotation of {sierra_path}\n")?,
+        }
+        write!(annotation, "{table}")?;
+        if unknown_executions {
+            writeln!(
+                annotation,
+                "\n- statement compiles to no CASM instructions, so its executions are not known"
+            )?;
+        }
+    }
+
+    Ok((!annotation.is_empty()).then_some(annotation))
+}
+
+/// Returns executions of statements compiling to no CASM instructions (e.g. `dup` or `drop`),
+/// which never show up in the trace, keyed by their indices. A statement always falling through
+/// to the next one in the same block is executed as many times as it. For other statements
+/// executions are not known, and `None` is returned.
+fn executions_of_statements_without_casm(
+    program: &Program,
+    casm_debug_info: &CairoProgramDebugInfo,
+    statement_costs: &HashMap<StatementIdx, StatementCosts>,
+) -> HashMap<usize, Option<usize>> {
+    // Statements jumped to start new blocks, so they may be executed more times than preceding ones
+    let branch_targets: HashSet<usize> = program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            GenStatement::Invocation(invocation) => Some(&invocation.branches),
+            GenStatement::Return(_) => None,
+        })
+        .flatten()
+        .filter_map(|branch| match branch.target {
+            BranchTarget::Statement(statement_idx) => Some(statement_idx.0),
+            BranchTarget::Fallthrough => None,
+        })
+        .collect();
+
+    let mut executions = HashMap::new();
+    // Following statements are visited first, so chains of statements without CASM are resolved
+    for (idx, statement_info) in casm_debug_info
+        .sierra_statement_info
+        .iter()
+        .enumerate()
+        .rev()
+    {
+        if statement_info.start_offset != statement_info.end_offset {
+            continue;
+        }
+        let falls_through = match &program.statements[idx] {
+            GenStatement::Invocation(invocation) => matches!(
+                invocation.branches.as_slice(),
+                [branch] if branch.target == BranchTarget::Fallthrough
+            ),
+            GenStatement::Return(_) => false,
+        };
+        let next_idx = idx + 1;
+        let statement_executions = if falls_through
+            && next_idx < program.statements.len()
+            && !branch_targets.contains(&next_idx)
+        {
+            executions.get(&next_idx).copied().unwrap_or_else(|| {
+                Some(
+                    statement_costs
+                        .get(&StatementIdx(next_idx))
+                        .map_or(0, |costs| costs.executions),
+                )
+            })
+        } else {
+            None
+        };
+        executions.insert(idx, statement_executions);
+    }
+    executions
+}
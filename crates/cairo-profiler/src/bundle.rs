@@ -0,0 +1,149 @@
+//! Self-contained trace bundles.
+//!
+//! A bundle is a gzip-compressed tar archive with the trace saved as `trace.json` and all sierra
+//! artifacts it references saved in the `sierra` directory. Sierra paths in the bundled trace are
+//! relative to the root of the archive.
+use crate::sierra_loader::path_resolver::{PathPrefixRemap, SierraPathResolver};
+use anyhow::{Context, Result, anyhow};
+use cairo_annotations::trace_data::{CallTraceNode, CallTraceV1, VersionedCallTrace};
+use camino::{Utf8Path, Utf8PathBuf};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use tempfile::TempDir;
+
+pub const BUNDLE_TRACE_FILE: &str = "trace.json";
+pub const BUNDLE_SIERRA_DIR: &str = "sierra";
+
+/// Offset and value of the magic bytes of tar archives in the ustar format.
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8] = b"ustar";
+/// Size of a tar header block.
+pub const TAR_HEADER_SIZE: usize = 512;
+
+pub fn is_bundle(header: &[u8]) -> bool {
+    header
+        .get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + TAR_MAGIC.len())
+        .is_some_and(|magic| magic == TAR_MAGIC)
+}
+
+/// Packs the trace and all sierra artifacts it references into a bundle saved under `output_path`.
+pub fn create_bundle(
+    mut trace: CallTraceV1,
+    path_resolver: &SierraPathResolver,
+    output_path: &Utf8Path,
+) -> Result<()> {
+    let mut sierra_paths = vec![];
+    collect_sierra_paths(&mut trace, &mut sierra_paths);
+
+    // Different paths from the trace may point to the same artifact.
+    let mut archive_paths_by_absolute_path: HashMap<Utf8PathBuf, Utf8PathBuf> = HashMap::new();
+    let mut taken_archive_paths = HashSet::new();
+    let mut artifacts = vec![];
+    for sierra_path in sierra_paths {
+        let absolute_path = path_resolver.resolve(sierra_path)?;
+        let archive_path = archive_paths_by_absolute_path
+            .entry(absolute_path.clone())
+            .or_insert_with(|| {
+                let file_name = absolute_path.file_name().unwrap_or("sierra.json");
+                let mut archive_path = Utf8Path::new(BUNDLE_SIERRA_DIR).join(file_name);
+                // Artifacts with the same name from different directories must not overwrite each other.
+                let mut index = 1;
+                while !taken_archive_paths.insert(archive_path.clone()) {
+                    archive_path =
+                        Utf8Path::new(BUNDLE_SIERRA_DIR).join(format!("{index}_{file_name}"));
+                    index += 1;
+                }
+                artifacts.push((absolute_path.clone(), archive_path.clone()));
+                archive_path
+            });
+        *sierra_path = archive_path.clone();
+    }
+
+    let serialized_trace = serde_json::to_vec(&VersionedCallTrace::V1(trace))
+        .context("Failed to serialize call trace")?;
+
+    let file = File::create(output_path)
+        .with_context(|| format!("Failed to create bundle file: {output_path}"))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    append_file(&mut archive, BUNDLE_TRACE_FILE, &serialized_trace)?;
+    for (absolute_path, archive_path) in artifacts {
+        let content = std::fs::read(&absolute_path)
+            .with_context(|| format!("Failed to read sierra artifact: {absolute_path}"))?;
+        append_file(&mut archive, archive_path.as_str(), &content)?;
+    }
+    archive
+        .into_inner()
+        .and_then(GzEncoder::finish)
+        .with_context(|| format!("Failed to write bundle file: {output_path}"))?;
+
+    Ok(())
+}
+
+fn collect_sierra_paths<'a>(
+    trace: &'a mut CallTraceV1,
+    sierra_paths: &mut Vec<&'a mut Utf8PathBuf>,
+) {
+    if let Some(cairo_execution_info) = &mut trace.cairo_execution_info {
+        sierra_paths.push(&mut cairo_execution_info.source_sierra_path);
+    }
+
+    for sub_trace_node in &mut trace.nested_calls {
+        if let CallTraceNode::EntryPointCall(sub_trace) = sub_trace_node {
+            collect_sierra_paths(sub_trace, sierra_paths);
+        }
+    }
+}
+
+fn append_file(
+    archive: &mut tar::Builder<GzEncoder<File>>,
+    archive_path: &str,
+    content: &[u8],
+) -> Result<()> {
+    // Headers are left without modification times and owners, so bundles are reproducible.
+    let mut header = tar::Header::new_ustar();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    archive
+        .append_data(&mut header, archive_path, content)
+        .with_context(|| format!("Failed to add {archive_path} to the bundle"))
+}
+
+/// A bundle extracted to a temporary directory, which is removed when the bundle is dropped.
+pub struct ExtractedBundle {
+    dir: TempDir,
+}
+
+impl ExtractedBundle {
+    /// Extracts the (already decompressed) tar archive from `reader`.
+    pub fn extract(reader: impl Read) -> Result<Self> {
+        let dir = TempDir::new().context("Failed to create a directory for the bundle")?;
+        tar::Archive::new(reader)
+            .unpack(dir.path())
+            .context("Failed to extract the bundle")?;
+        Ok(Self { dir })
+    }
+
+    pub fn trace_path(&self) -> Result<Utf8PathBuf> {
+        let trace_path = self.root()?.join(BUNDLE_TRACE_FILE);
+        if !trace_path.is_file() {
+            return Err(anyhow!("Bundle does not contain {BUNDLE_TRACE_FILE}"));
+        }
+        Ok(trace_path)
+    }
+
+    /// Remap making sierra paths saved in the bundled trace point to the extracted artifacts.
+    pub fn path_prefix_remap(&self) -> Result<PathPrefixRemap> {
+        Ok(PathPrefixRemap {
+            from: BUNDLE_SIERRA_DIR.into(),
+            to: self.root()?.join(BUNDLE_SIERRA_DIR),
+        })
+    }
+
+    fn root(&self) -> Result<&Utf8Path> {
+        Utf8Path::from_path(self.dir.path())
+            .ok_or_else(|| anyhow!("Path of the temporary directory is not valid UTF-8"))
+    }
+}
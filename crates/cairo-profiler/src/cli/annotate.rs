@@ -0,0 +1,66 @@
+use crate::annotation::annotate;
+use crate::cli::trace_args::TraceArgs;
+use crate::sierra_loader::{PrecomputeConfig, collect_and_compile_all_sierra_programs};
+use crate::trace_input::{open_trace, read_call_trace};
+use crate::trace_reader::is_sierra_gas_tracked;
+use crate::ui;
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+#[derive(Args, Debug)]
+pub struct Annotate {
+    /// Path to .json with trace data. Gzip and zstd compressed files are accepted as well,
+    /// as are bundles created with `cairo-profiler bundle`.
+    /// Use `-` to read the trace from the standard input.
+    pub path_to_trace_data: Utf8PathBuf,
+
+    /// Annotate only the function with the given full name, e.g. `my_contract::MyContract::my_function`,
+    /// instead of whole programs executed in the trace.
+    #[arg(long)]
+    pub function: Option<String>,
+
+    #[command(flatten)]
+    pub trace_args: TraceArgs,
+}
+
+pub fn run_annotate(args: &Annotate) -> Result<()> {
+    let versioned_constants = args.trace_args.read_versioned_constants()?;
+
+    let trace_input =
+        open_trace(&args.path_to_trace_data).context("Failed to read call trace from a file")?;
+    let path_resolver = trace_input.path_resolver(
+        &args.trace_args.remap_path_prefix,
+        &args.trace_args.sierra_search_dir,
+    )?;
+    let trace = read_call_trace(trace_input.reader)?;
+
+    let cairo_enable_gas = trace
+        .cairo_execution_info
+        .as_ref()
+        .and_then(|info| info.enable_gas)
+        .unwrap_or(true);
+    let compiled_artifacts_cache = collect_and_compile_all_sierra_programs(
+        &trace,
+        cairo_enable_gas,
+        path_resolver,
+        PrecomputeConfig::default(),
+    )?;
+
+    let annotation = annotate(
+        &trace,
+        &compiled_artifacts_cache,
+        &versioned_constants,
+        args.function.as_deref(),
+        cairo_enable_gas,
+        is_sierra_gas_tracked(&trace),
+    )?;
+    match (annotation, &args.function) {
+        (Some(annotation), _) => ui::msg(annotation),
+        (None, Some(function_name)) => ui::warn(format!(
+            "Function {function_name} was not executed in the trace"
+        )),
+        (None, None) => ui::warn("No sierra programs were executed in the trace"),
+    }
+    Ok(())
+}
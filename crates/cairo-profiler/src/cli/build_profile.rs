@@ -1,21 +1,42 @@
-use crate::profile_builder::{build_profile, save_profile};
+use crate::cli::trace_args::TraceArgs;
+use crate::cost_comparison::merge_compared_samples;
+use crate::cost_override::{CostOverride, apply_cost_overrides, parse_cost_override};
+use crate::disassembly::{disassemble_function, save_disassembly};
+use crate::fee_estimation::{FeeUnit, GasPrices, add_fee_estimations, parse_price};
+use crate::libfunc_report::LibfuncReport;
+use crate::profile_builder::pprof::Profile;
+use crate::profile_builder::{ProfileMetadata, build_profile, save_profile};
 use crate::profile_viewer::print_profile;
 use crate::profiler_config::ProfilerConfig;
-use crate::sierra_loader::collect_and_compile_all_sierra_programs;
-use crate::trace_reader::collect_samples_from_trace;
+use crate::sierra_loader::path_resolver::SierraPathResolver;
+use crate::sierra_loader::{PrecomputeConfig, collect_and_compile_all_sierra_programs};
+use crate::trace_input::{open_trace, read_call_trace};
 use crate::trace_reader::function_name::ExternalTool;
+use crate::trace_reader::sample::{MeasurementUnit, Sample, aggregate_samples};
+use crate::trace_reader::streaming::read_and_profile_trace;
+use crate::trace_reader::{
+    ProfiledTrace, collect_function_level_profiling_infos, collect_libfunc_report,
+    collect_samples_from_profiled_trace, is_sierra_gas_tracked, warn_about_inaccurate_data,
+};
 use crate::ui;
-use crate::versioned_constants_reader::read_and_parse_versioned_constants_file;
-use anyhow::{Context, Result};
-use cairo_annotations::trace_data::VersionedCallTrace;
+use crate::versioned_constants_reader::{
+    StarknetVersion, VersionedConstants, read_and_parse_versioned_constants_file,
+};
+use anyhow::{Context, Result, anyhow};
+use cairo_annotations::trace_data::CallTraceV1;
 use camino::Utf8PathBuf;
 use clap::Args;
-use std::fs;
+use std::env;
 use std::num::NonZeroUsize;
 
 #[derive(Args, Debug)]
 pub struct BuildProfile {
-    /// Path to .json with trace data
+    /// Path to .json with trace data. Gzip and zstd compressed files are accepted as well,
+    /// as are bundles created with `cairo-profiler bundle`.
+    /// Use `-` to read the trace from the standard input.
+    // Clap leaves the group of arguments empty for structs with flattened arguments, so the trace
+    // is added to it explicitly for `Cli` to recognize the arguments given without a subcommand.
+    #[arg(group = "BuildProfile")]
     pub path_to_trace_data: Utf8PathBuf,
 
     /// Path to the output file
@@ -31,22 +52,55 @@ pub struct BuildProfile {
     #[arg(long, default_value_t = 100)]
     pub max_function_stack_trace_depth: usize,
 
+    /// Fold recursive calls made from inlined functions in function level profiling: a function
+    /// calling itself through functions inlined into it is attributed to its calling frame, as
+    /// direct recursive calls are. Only the top frame of the stack is folded - mutually recursive
+    /// functions are kept on the stack.
+    #[arg(long)]
+    pub collapse_recursion: bool,
+
+    /// Show loops as separate functions named after their parent function and the line they start
+    /// at, e.g. `my_fn::loop@line 42`, instead of merging them into their parent functions.
+    /// Lines are known only if the program was compiled with statements code locations debug info.
+    #[arg(long)]
+    pub show_loops: bool,
+
     /// Split non-inlined generic functions based on the type they were monomorphised with.
     /// E.g. treat `function<felt252>` as different from `function<u8>`.
     #[arg(long)]
     pub split_generics: bool,
 
+    /// Split repeated invocations of the same entrypoint, numbering them in order of execution.
+    /// E.g. treat the 3rd call of `transfer` as different from the 1st one.
+    #[arg(long)]
+    pub split_entrypoint_invocations: bool,
+
     /// Show inlined function in a trace tree. Requires Scarb >= 2.7.0-rc.0 and setting
     /// `unstable-add-statements-functions-debug-info = true` in `[cairo]` section of Scarb.toml.
     #[arg(long)]
     pub show_inlined_functions: bool,
 
-    /// Path to a file, that includes a map with cost of resources like syscalls.
-    /// If not provided, the cost map will default to the one used on Starknet 0.14.1.
-    /// Files for different Starknet versions can be found in the sequencer repo:
-    /// <https://github.com/starkware-libs/sequencer/blob/main/crates/blockifier/resources/>
+    #[command(flatten)]
+    pub trace_args: TraceArgs,
+
+    /// Price the trace under versioned constants of another Starknet version as well, to compare costs.
+    /// Measurements depending on versioned constants are saved for both versions, suffixed with them
+    /// (e.g. `sierra_gas@0.13.4` and `sierra_gas@0.14.1`).
     #[arg(long)]
-    pub versioned_constants_path: Option<Utf8PathBuf>,
+    pub compare_starknet_version: Option<StarknetVersion>,
+
+    /// Like `--compare-starknet-version`, but with versioned constants read from a file
+    /// (e.g. of an upcoming Starknet version). Measurements are suffixed with the file name.
+    #[arg(long, conflicts_with = "compare_starknet_version")]
+    pub compare_versioned_constants_path: Option<Utf8PathBuf>,
+
+    /// Override a single cost from versioned constants the profile is built with, to see how
+    /// changing it would affect the profile. Can be passed multiple times. Accepted forms are
+    /// `syscall:<syscall>.[calldata_factor.]<resource>=<value>` (e.g. `syscall:StorageWrite.n_steps=100`
+    /// or `syscall:Keccak.range_check_builtin=2`) and `builtin:<builtin>=<gas>` (e.g. `builtin:poseidon=500`).
+    /// Overrides are recorded in comments of the profile.
+    #[arg(long, value_parser = parse_cost_override)]
+    pub override_cost: Vec<CostOverride>,
 
     /// View the resulting profile.
     /// To view already-built profile run `cairo-profiler view`.
@@ -74,39 +128,464 @@ pub struct BuildProfile {
     /// Show libfuncs in the trace tree.
     #[arg(long)]
     pub show_libfuncs: bool,
+
+    /// Print a summary of libfuncs executed in the trace, grouped by their generic ids (e.g. `u256_safe_divmod`):
+    /// how many times they were called, their cost (including builtins they used) and functions calling them the most.
+    #[arg(long)]
+    pub libfunc_report: bool,
+
+    /// Save the summary of libfuncs (see `--libfunc-report`) as JSON under the given path.
+    #[arg(long)]
+    pub libfunc_report_path: Option<Utf8PathBuf>,
+
+    /// Print the CASM disassembly of the function with the given name (e.g. `my_contract::MyContract::transfer`),
+    /// with the number of times each of its CASM instructions and sierra statements was executed in the trace.
+    #[arg(long, conflicts_with = "stream")]
+    pub disassemble: Option<String>,
+
+    /// Save the disassembly (see `--disassemble`) under the given path instead of printing it.
+    #[arg(long, requires = "disassemble")]
+    pub disassembly_path: Option<Utf8PathBuf>,
+
+    /// Show storage variables accessed by `StorageRead` and `StorageWrite` syscalls as their children
+    /// in the trace tree. Traces do not contain storage keys, so variables are recognised by constants
+    /// their addresses are computed from - variables not recognised this way are shown as unknown.
+    /// Compiled contracts do not list their storage variables, so names of variables are a best-effort
+    /// guess - identifiers from the ABI and debug info of the contract which hash to their addresses,
+    /// marked with a trailing `?` (e.g. `balances?`).
+    #[arg(long)]
+    pub show_storage_variables: bool,
+
+    /// Name of a storage variable, used to label accesses to it (e.g. `balances`, or `ERC20_balances`
+    /// for a variable of a component). Accesses to variables which names are not given are labelled
+    /// with their base address, or as unknown for `Map`s. Can be passed multiple times.
+    #[arg(long, requires = "show_storage_variables")]
+    pub storage_variable: Vec<String>,
+
+    /// Treat constructors called directly by the test code as `DEPLOY_ACCOUNT` transactions and charge
+    /// l2 gas for their calldata, shown in a separate node under the constructor.
+    #[arg(long)]
+    pub deploy_account_costs: bool,
+
+    /// Charge l2 gas for declaring every contract class executed in the trace (its sierra program, CASM
+    /// bytecode and ABI), shown in a separate node under the transaction entrypoint which executes
+    /// the class first.
+    #[arg(long)]
+    pub declare_costs: bool,
+
+    /// Keep a separate sample for every call, instead of merging samples with identical call stacks
+    /// (e.g. of an entrypoint called repeatedly) into one. Makes the profile larger and slower to load.
+    #[arg(long)]
+    pub no_sample_aggregation: bool,
+
+    /// Read the trace incrementally, profiling each call as soon as it is read.
+    /// Keeps memory usage low for very large traces, but calls are profiled one after another.
+    #[arg(long)]
+    pub stream: bool,
+
+    /// Estimate fees of calls and functions, adding the `fee_fri` (or `fee_wei`) sample.
+    /// Path to a JSON file with gas prices, in the format of Starknet block headers
+    /// (`l1_gas_price`, `l1_data_gas_price` and `l2_gas_price` objects with `price_in_fri` and `price_in_wei`).
+    #[arg(long)]
+    pub gas_prices_path: Option<Utf8PathBuf>,
+
+    /// Price of L1 gas used for fee estimation, in `--fee-unit`. Takes precedence over `--gas-prices-path`.
+    #[arg(long, value_parser = parse_price)]
+    pub l1_gas_price: Option<u128>,
+
+    /// Price of L1 data gas used for fee estimation, in `--fee-unit`. Takes precedence over `--gas-prices-path`.
+    #[arg(long, value_parser = parse_price)]
+    pub l1_data_gas_price: Option<u128>,
+
+    /// Price of L2 gas used for fee estimation, in `--fee-unit`. Takes precedence over `--gas-prices-path`.
+    #[arg(long, value_parser = parse_price)]
+    pub l2_gas_price: Option<u128>,
+
+    /// Unit of estimated fees.
+    #[arg(long, value_enum, default_value_t = FeeUnit::Fri)]
+    pub fee_unit: FeeUnit,
+}
+
+impl BuildProfile {
+    /// Returns versioned constants to compare costs with and their label, if comparison was requested.
+    fn compared_versioned_constants(&self) -> Result<Option<(String, VersionedConstants)>> {
+        if self.compare_starknet_version.is_none()
+            && self.compare_versioned_constants_path.is_none()
+        {
+            return Ok(None);
+        }
+
+        let label = versioned_constants_label(
+            self.compare_versioned_constants_path.as_ref(),
+            self.compare_starknet_version,
+        );
+        if label == self.versioned_constants_label() {
+            return Err(anyhow!(
+                "Compared versioned constants must differ from the ones the profile is built with, both are {label}"
+            ));
+        }
+
+        let versioned_constants = read_and_parse_versioned_constants_file(
+            self.compare_versioned_constants_path.as_ref(),
+            self.compare_starknet_version,
+        )
+        .context("Failed to get resource map from compared versioned constants file")?;
+        Ok(Some((label, versioned_constants)))
+    }
+
+    /// Label of versioned constants the profile is built with.
+    /// Constants with overridden costs are distinguished from the ones they were read from.
+    fn versioned_constants_label(&self) -> String {
+        let label = versioned_constants_label(
+            self.trace_args.versioned_constants_path.as_ref(),
+            self.trace_args.starknet_version,
+        );
+        if self.override_cost.is_empty() {
+            label
+        } else {
+            format!("{label}+overrides")
+        }
+    }
+
+    fn profile_metadata(
+        &self,
+        external_tool: ExternalTool,
+        sierra_gas_tracking: bool,
+    ) -> Result<ProfileMetadata> {
+        Ok(ProfileMetadata {
+            comments: self.provenance_comments(external_tool, sierra_gas_tracking),
+            default_sample_type: self.default_sample_type(sierra_gas_tracking),
+            time_nanos: build_time_nanos()?,
+        })
+    }
+
+    /// Returns human-readable lines describing how the profile is built.
+    fn provenance_comments(
+        &self,
+        external_tool: ExternalTool,
+        sierra_gas_tracking: bool,
+    ) -> Vec<String> {
+        let mut comments = vec![
+            format!("cairo-profiler version: {}", env!("CARGO_PKG_VERSION")),
+            format!("trace: {}", self.path_to_trace_data),
+            format!("external tool: {external_tool}"),
+            format!(
+                "versioned constants: {}",
+                versioned_constants_description(
+                    self.trace_args.versioned_constants_path.as_ref(),
+                    self.trace_args.starknet_version
+                )
+            ),
+        ];
+        if self.compare_starknet_version.is_some()
+            || self.compare_versioned_constants_path.is_some()
+        {
+            comments.push(format!(
+                "compared versioned constants: {}",
+                versioned_constants_description(
+                    self.compare_versioned_constants_path.as_ref(),
+                    self.compare_starknet_version
+                )
+            ));
+        }
+        comments.extend(
+            self.override_cost
+                .iter()
+                .map(|cost_override| format!("cost override: {cost_override}")),
+        );
+        comments.push(format!(
+            "sierra gas tracking: {}",
+            if sierra_gas_tracking {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        ));
+
+        let enabled_flags: Vec<&str> = [
+            (self.show_details, "--show-details"),
+            (self.split_generics, "--split-generics"),
+            (self.collapse_recursion, "--collapse-recursion"),
+            (self.show_loops, "--show-loops"),
+            (
+                self.split_entrypoint_invocations,
+                "--split-entrypoint-invocations",
+            ),
+            (self.show_inlined_functions, "--show-inlined-functions"),
+            (self.show_libfuncs, "--show-libfuncs"),
+            (self.show_storage_variables, "--show-storage-variables"),
+            (self.deploy_account_costs, "--deploy-account-costs"),
+            (self.declare_costs, "--declare-costs"),
+            (self.no_sample_aggregation, "--no-sample-aggregation"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, flag)| enabled.then_some(flag))
+        .collect();
+        comments.push(format!(
+            "enabled flags: {}",
+            if enabled_flags.is_empty() {
+                "none".to_string()
+            } else {
+                enabled_flags.join(" ")
+            }
+        ));
+        comments.push(format!(
+            "max function stack trace depth: {}",
+            self.max_function_stack_trace_depth
+        ));
+
+        comments
+    }
+
+    /// Returns the sample most relevant for the trace - sierra gas if it was tracked, steps otherwise.
+    fn default_sample_type(&self, sierra_gas_tracking: bool) -> MeasurementUnit {
+        let unit = if sierra_gas_tracking {
+            "sierra_gas"
+        } else {
+            "steps"
+        };
+        if self.compare_starknet_version.is_some()
+            || self.compare_versioned_constants_path.is_some()
+        {
+            MeasurementUnit(format!("{unit}@{}", self.versioned_constants_label()))
+        } else {
+            MeasurementUnit(unit.to_string())
+        }
+    }
+
+    /// Returns gas prices if fee estimation was requested.
+    fn gas_prices(&self) -> Result<Option<GasPrices>> {
+        if self.gas_prices_path.is_none()
+            && self.l1_gas_price.is_none()
+            && self.l1_data_gas_price.is_none()
+            && self.l2_gas_price.is_none()
+        {
+            return Ok(None);
+        }
+        GasPrices::new(
+            self.fee_unit,
+            self.gas_prices_path.as_deref(),
+            self.l1_gas_price,
+            self.l1_data_gas_price,
+            self.l2_gas_price,
+        )
+        .map(Some)
+    }
 }
 
 pub fn run_build_profile(args: &BuildProfile) -> Result<()> {
-    let data = fs::read_to_string(&args.path_to_trace_data)
-        .context("Failed to read call trace from a file")?;
-    let versioned_constants =
-        read_and_parse_versioned_constants_file(args.versioned_constants_path.as_ref())
-            .context("Failed to get resource map from versioned constants file")?;
-    let VersionedCallTrace::V1(serialized_trace) =
-        serde_json::from_str(&data).context("Failed to deserialize call trace")?;
-
-    let cairo_enable_gas = serialized_trace
+    let mut versioned_constants = args.trace_args.read_versioned_constants()?;
+    apply_cost_overrides(&mut versioned_constants, &args.override_cost)?;
+    let gas_prices = args.gas_prices()?;
+    let compared_versioned_constants = args.compared_versioned_constants()?;
+
+    let trace_input =
+        open_trace(&args.path_to_trace_data).context("Failed to read call trace from a file")?;
+    let path_resolver = trace_input.path_resolver(
+        &args.trace_args.remap_path_prefix,
+        &args.trace_args.sierra_search_dir,
+    )?;
+
+    let profiled_trace = if args.stream {
+        read_and_profile_trace(trace_input.reader, args, path_resolver)?
+    } else {
+        profile_trace(read_call_trace(trace_input.reader)?, args, path_resolver)?
+    };
+    warn_about_incomplete_data(&profiled_trace);
+
+    // Calls are profiled once, and their samples are priced under each versioned constants
+    let collect_priced_samples = |versioned_constants| {
+        let samples = collect_samples_from_profiled_trace(&profiled_trace, versioned_constants)?;
+        with_fee_estimations(samples, gas_prices.as_ref())
+    };
+    let samples = collect_priced_samples(&versioned_constants)?;
+    let samples = match &compared_versioned_constants {
+        Some((compared_label, compared_versioned_constants)) => merge_compared_samples(
+            (&args.versioned_constants_label(), samples),
+            (
+                compared_label,
+                collect_priced_samples(compared_versioned_constants)?,
+            ),
+        ),
+        None => samples,
+    };
+    let sierra_gas_tracking = is_sierra_gas_tracked(&profiled_trace.trace);
+    // The libfunc report covers only the versioned constants the profile is built with
+    let libfunc_report = collect_libfunc_report(
+        &profiled_trace.function_level_profiling_infos,
+        &versioned_constants,
+        sierra_gas_tracking,
+    );
+
+    let ProfiledTrace {
+        trace,
+        profiler_config,
+        compiled_artifacts_cache,
+        ..
+    } = &profiled_trace;
+    let disassembly = match &args.disassemble {
+        Some(function_name) => {
+            disassemble_function(trace, compiled_artifacts_cache, function_name)?
+        }
+        None => None,
+    };
+    let samples = if args.no_sample_aggregation {
+        samples
+    } else {
+        aggregate_samples(samples)
+    };
+
+    let metadata = args.profile_metadata(profiler_config.external_tool, sierra_gas_tracking)?;
+    let profile = build_profile(&samples, &metadata);
+    save_profile(&args.output_path, &profile).context("Failed to write profile data to file")?;
+
+    output_reports(
+        args,
+        &profile,
+        &libfunc_report,
+        sierra_gas_tracking,
+        disassembly.as_deref(),
+    )
+}
+
+/// Prints the profile and saves or prints the libfunc report and the disassembly, if requested.
+fn output_reports(
+    args: &BuildProfile,
+    profile: &Profile,
+    libfunc_report: &LibfuncReport,
+    sierra_gas_tracking: bool,
+    disassembly: Option<&str>,
+) -> Result<()> {
+    if args.view {
+        print_profile(
+            profile,
+            &args.sample,
+            args.limit,
+            args.hide.as_deref(),
+            false,
+        )?;
+    }
+    if args.libfunc_report {
+        libfunc_report.print(sierra_gas_tracking);
+    }
+    if let Some(path) = &args.libfunc_report_path {
+        libfunc_report.save(path)?;
+    }
+    if let Some(function_name) = &args.disassemble {
+        match (disassembly, &args.disassembly_path) {
+            (Some(disassembly), Some(path)) => save_disassembly(disassembly, path)?,
+            (Some(disassembly), None) => ui::msg(disassembly),
+            (None, _) => ui::warn(format!(
+                "Function {function_name} was not executed in the trace"
+            )),
+        }
+    }
+    Ok(())
+}
+
+/// Profiles calls of the trace on function level.
+fn profile_trace(
+    trace: CallTraceV1,
+    args: &BuildProfile,
+    path_resolver: SierraPathResolver,
+) -> Result<ProfiledTrace> {
+    let cairo_enable_gas = trace
         .cairo_execution_info
         .as_ref()
         .and_then(|info| info.enable_gas)
         .unwrap_or(true);
     let external_tool =
-        ExternalTool::from_contract_prefix(serialized_trace.entry_point.contract_name.as_deref())?;
+        ExternalTool::from_contract_prefix(trace.entry_point.contract_name.as_deref())?;
     let profiler_config = ProfilerConfig::new(args, cairo_enable_gas, external_tool);
 
-    let missing_calldata_factors = serialized_trace.entry_point.calldata_len.is_none();
+    let compiled_artifacts_cache = collect_and_compile_all_sierra_programs(
+        &trace,
+        profiler_config.cairo_enable_gas,
+        path_resolver,
+        PrecomputeConfig::from(args),
+    )?;
+    let function_level_profiling_infos = collect_function_level_profiling_infos(
+        &trace,
+        &compiled_artifacts_cache,
+        &profiler_config,
+    )?;
+
+    Ok(ProfiledTrace {
+        trace,
+        profiler_config,
+        compiled_artifacts_cache,
+        function_level_profiling_infos,
+    })
+}
+
+fn with_fee_estimations(
+    mut samples: Vec<Sample>,
+    gas_prices: Option<&GasPrices>,
+) -> Result<Vec<Sample>> {
+    if let Some(gas_prices) = gas_prices {
+        add_fee_estimations(&mut samples, gas_prices)?;
+    }
+    Ok(samples)
+}
+
+/// Label of versioned constants read from `path`, or embedded for `starknet_version`.
+fn versioned_constants_label(
+    path: Option<&Utf8PathBuf>,
+    starknet_version: Option<StarknetVersion>,
+) -> String {
+    match path {
+        Some(path) => path.file_stem().unwrap_or(path.as_str()).to_string(),
+        None => starknet_version
+            .unwrap_or(StarknetVersion::DEFAULT)
+            .to_string(),
+    }
+}
+
+/// Description of versioned constants read from `path`, or embedded for `starknet_version`.
+fn versioned_constants_description(
+    path: Option<&Utf8PathBuf>,
+    starknet_version: Option<StarknetVersion>,
+) -> String {
+    match path {
+        Some(path) => format!("file {path}"),
+        None => format!(
+            "Starknet {}",
+            starknet_version.unwrap_or(StarknetVersion::DEFAULT)
+        ),
+    }
+}
+
+/// Returns the time the profile is built at, in nanoseconds since the Unix epoch, taken from
+/// `SOURCE_DATE_EPOCH` (in seconds). The time is left unset (zero) by default, so profiles built
+/// from the same trace are identical.
+fn build_time_nanos() -> Result<i64> {
+    if let Ok(source_date_epoch) = env::var("SOURCE_DATE_EPOCH") {
+        let seconds: i64 = source_date_epoch
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid SOURCE_DATE_EPOCH `{source_date_epoch}`"))?;
+        return seconds
+            .checked_mul(1_000_000_000)
+            .context("SOURCE_DATE_EPOCH does not fit in i64 nanoseconds");
+    }
+    Ok(0)
+}
+
+fn warn_about_incomplete_data(profiled_trace: &ProfiledTrace) {
+    let ProfiledTrace {
+        trace,
+        profiler_config,
+        compiled_artifacts_cache,
+        ..
+    } = profiled_trace;
+    let missing_calldata_factors = trace.entry_point.calldata_len.is_none();
     let using_snforge_for_trace = profiler_config.external_tool == ExternalTool::Snforge;
-    if missing_calldata_factors && cairo_enable_gas && using_snforge_for_trace {
+    if missing_calldata_factors && profiler_config.cairo_enable_gas && using_snforge_for_trace {
         ui::warn(
             "Missing calldata_factors for scaled syscalls - resource estimations may not be accurate. Consider using snforge 0.48+ for trace generation.",
         );
     }
 
-    let compiled_artifacts_cache = collect_and_compile_all_sierra_programs(
-        &serialized_trace,
-        profiler_config.cairo_enable_gas,
-    )?;
-
     if profiler_config.show_inlined_functions
         && !compiled_artifacts_cache.statements_functions_maps_are_present()
     {
@@ -117,19 +596,5 @@ pub fn run_build_profile(args: &BuildProfile) -> Result<()> {
         );
     }
 
-    let samples = collect_samples_from_trace(
-        &serialized_trace,
-        &compiled_artifacts_cache,
-        &profiler_config,
-        &versioned_constants,
-    )?;
-
-    let profile = build_profile(&samples);
-    save_profile(&args.output_path, &profile).context("Failed to write profile data to file")?;
-
-    if args.view {
-        print_profile(&profile, &args.sample, args.limit, args.hide.as_deref())?;
-    }
-
-    Ok(())
+    warn_about_inaccurate_data(profiled_trace);
 }
@@ -0,0 +1,45 @@
+use crate::bundle::create_bundle;
+use crate::sierra_loader::path_resolver::{PathPrefixRemap, SierraPathResolver};
+use crate::trace_input::{open_trace, read_call_trace};
+use anyhow::{Context, Result, anyhow};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+#[derive(Args, Debug)]
+pub struct Bundle {
+    /// Path to .json with trace data. Gzip and zstd compressed files are accepted as well.
+    /// Use `-` to read the trace from the standard input.
+    pub path_to_trace_data: Utf8PathBuf,
+
+    /// Path to the output bundle
+    #[arg(short, long, default_value = "trace_bundle.tar.gz")]
+    pub output_path: Utf8PathBuf,
+
+    /// Replace the `FROM` prefix of sierra paths saved in the trace with `TO`.
+    /// Useful for traces generated on another machine, e.g. in CI or a Docker container.
+    /// Can be passed multiple times - if several prefixes match, the last one wins.
+    #[arg(long, value_name = "FROM=TO")]
+    pub remap_path_prefix: Vec<PathPrefixRemap>,
+
+    /// Directory to look for sierra artifacts in (by file name) when a sierra path saved in the trace
+    /// does not exist. Can be passed multiple times.
+    #[arg(long)]
+    pub sierra_search_dir: Vec<Utf8PathBuf>,
+}
+
+pub fn run_bundle(args: &Bundle) -> Result<()> {
+    let trace_input =
+        open_trace(&args.path_to_trace_data).context("Failed to read call trace from a file")?;
+    if trace_input.bundle.is_some() {
+        return Err(anyhow!("{} is already a bundle", args.path_to_trace_data));
+    }
+
+    create_bundle(
+        read_call_trace(trace_input.reader)?,
+        &SierraPathResolver::new(
+            args.remap_path_prefix.clone(),
+            args.sierra_search_dir.clone(),
+        ),
+        &args.output_path,
+    )
+}
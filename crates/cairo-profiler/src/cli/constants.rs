@@ -0,0 +1,29 @@
+use crate::ui;
+use crate::versioned_constants_reader::StarknetVersion;
+use clap::{Args, Subcommand, ValueEnum};
+
+#[derive(Args)]
+pub struct Constants {
+    #[command(subcommand)]
+    pub command: ConstantsCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ConstantsCommand {
+    /// List Starknet versions, which versioned constants can be selected with `--starknet-version`
+    List,
+}
+
+pub fn run_constants(args: &Constants) {
+    match args.command {
+        ConstantsCommand::List => {
+            for &version in StarknetVersion::value_variants() {
+                if version == StarknetVersion::DEFAULT {
+                    ui::msg(format!("{version} (default)"));
+                } else {
+                    ui::msg(version);
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,15 @@
+use crate::cli::annotate::Annotate;
 use crate::cli::build_profile::BuildProfile;
+use crate::cli::bundle::Bundle;
+use crate::cli::constants::Constants;
 use crate::cli::view::ViewProfile;
 use clap::{Parser, Subcommand};
 
+pub(crate) mod annotate;
 pub(crate) mod build_profile;
+pub(crate) mod bundle;
+pub(crate) mod constants;
+pub(crate) mod trace_args;
 pub(crate) mod view;
 
 #[derive(Parser)]
@@ -19,7 +26,15 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Build the profile from provided trace data
-    BuildProfile(BuildProfile),
+    BuildProfile(Box<BuildProfile>),
     /// View built profile
     View(ViewProfile),
+    /// Pack the trace and all sierra artifacts it references into a single archive,
+    /// which can be passed to `build-profile` instead of the trace
+    Bundle(Bundle),
+    /// Inspect versioned constants embedded in the binary
+    Constants(Constants),
+    /// Print sierra statements of programs executed in the trace (or of a single function),
+    /// annotated with the number of times they were executed and their costs
+    Annotate(Annotate),
 }
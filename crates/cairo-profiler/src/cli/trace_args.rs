@@ -0,0 +1,45 @@
+use crate::sierra_loader::path_resolver::PathPrefixRemap;
+use crate::versioned_constants_reader::{
+    StarknetVersion, VersionedConstants, read_and_parse_versioned_constants_file,
+};
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+/// Arguments shared by subcommands pricing traces: versioned constants the trace is priced with
+/// and locations of sierra artifacts it references.
+#[derive(Args, Debug)]
+pub struct TraceArgs {
+    /// Path to a file, that includes a map with cost of resources like syscalls.
+    /// If not provided, the cost map embedded for `--starknet-version` is used.
+    /// Files for different Starknet versions can be found in the sequencer repo:
+    /// <https://github.com/starkware-libs/sequencer/blob/main/crates/blockifier/resources/>
+    #[arg(long)]
+    pub versioned_constants_path: Option<Utf8PathBuf>,
+
+    /// Starknet version, which embedded cost map is used. Defaults to 0.14.1.
+    /// To see all available versions run `cairo-profiler constants list`.
+    #[arg(long, conflicts_with = "versioned_constants_path")]
+    pub starknet_version: Option<StarknetVersion>,
+
+    /// Replace the `FROM` prefix of sierra paths saved in the trace with `TO`.
+    /// Useful for traces generated on another machine, e.g. in CI or a Docker container.
+    /// Can be passed multiple times - if several prefixes match, the last one wins.
+    #[arg(long, value_name = "FROM=TO")]
+    pub remap_path_prefix: Vec<PathPrefixRemap>,
+
+    /// Directory to look for sierra artifacts in (by file name) when a sierra path saved in the trace
+    /// does not exist. Can be passed multiple times.
+    #[arg(long)]
+    pub sierra_search_dir: Vec<Utf8PathBuf>,
+}
+
+impl TraceArgs {
+    pub fn read_versioned_constants(&self) -> Result<VersionedConstants> {
+        read_and_parse_versioned_constants_file(
+            self.versioned_constants_path.as_ref(),
+            self.starknet_version,
+        )
+        .context("Failed to get resource map from versioned constants file")
+    }
+}
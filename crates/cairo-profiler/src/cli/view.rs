@@ -1,4 +1,4 @@
-use crate::profile_viewer::{get_samples, load_profile, print_profile};
+use crate::profile_viewer::{get_info, get_samples, load_profile, print_profile};
 use crate::ui;
 use anyhow::Result;
 use camino::Utf8PathBuf;
@@ -12,20 +12,31 @@ pub struct ViewProfile {
 
     /// Show the sample in the top view.
     /// To get the list of available samples use `--list-samples`.
-    #[arg(long, default_value = "calls", conflicts_with = "list_samples")]
+    #[arg(long, default_value = "calls", conflicts_with_all = ["list_samples", "info"])]
     pub sample: String,
 
     /// List all the samples included in the profile.
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "info")]
     pub list_samples: bool,
 
+    /// Show information about how the profile was built, e.g. the trace and versioned constants
+    /// it was built from, enabled flags and the default sample.
+    #[arg(long)]
+    pub info: bool,
+
     /// Set a limit of nodes showed in the top view.
-    #[arg(long, default_value = "10", conflicts_with = "list_samples")]
+    #[arg(long, default_value = "10", conflicts_with_all = ["list_samples", "info"])]
     pub limit: NonZeroUsize,
 
     /// Skip nodes matching regex
-    #[arg(long, conflicts_with = "list_samples")]
+    #[arg(long, conflicts_with_all = ["list_samples", "info"])]
     pub hide: Option<String>,
+
+    /// Show the number of calls of each node and its flat and cumulative values divided by it.
+    /// Calls are taken from `calls`, `syscall usage` and `function calls` samples, or counted
+    /// from sample stacks for nodes without them.
+    #[arg(long, conflicts_with_all = ["list_samples", "info"])]
+    pub per_call: bool,
 }
 
 pub fn run_view(args: &ViewProfile) -> Result<()> {
@@ -35,6 +46,16 @@ pub fn run_view(args: &ViewProfile) -> Result<()> {
         ui::msg(samples.join("\n"));
         return Ok(());
     }
-    print_profile(&profile, &args.sample, args.limit, args.hide.as_deref())?;
+    if args.info {
+        ui::msg(get_info(&profile).join("\n"));
+        return Ok(());
+    }
+    print_profile(
+        &profile,
+        &args.sample,
+        args.limit,
+        args.hide.as_deref(),
+        args.per_call,
+    )?;
     Ok(())
 }
@@ -0,0 +1,98 @@
+//! Comparison of costs priced under different versioned constants.
+use crate::trace_reader::sample::{FunctionCall, MeasurementUnit, Sample};
+use std::collections::{HashMap, HashSet};
+
+/// Measurements depending on versioned constants - steps of syscalls and gas are taken from
+/// them. They are always suffixed, even if both versioned constants price them the same.
+const PRICED_UNITS: [&str; 3] = ["steps", "sierra_gas", "l2_gas"];
+/// Prefix of fee estimations (e.g. `fee_fri`), which are computed from priced gas.
+const FEE_UNIT_PREFIX: &str = "fee_";
+
+/// Merges samples of the same trace collected under two different versioned constants.
+/// Measurements depending on versioned constants are suffixed with labels of the constants they
+/// were priced under (e.g. `sierra_gas@0.14.1`), so both variants are kept in a single profile.
+/// Besides steps, gas and fees, these are all measurements whose values differ between the two
+/// versioned constants, e.g. memory holes and builtins used by syscalls. Other measurements are
+/// kept once, without a suffix.
+pub fn merge_compared_samples(
+    (baseline_label, baseline_samples): (&str, Vec<Sample>),
+    (compared_label, compared_samples): (&str, Vec<Sample>),
+) -> Vec<Sample> {
+    let mut priced_units = differing_units(&baseline_samples, &compared_samples);
+    priced_units.extend(
+        baseline_samples
+            .iter()
+            .chain(&compared_samples)
+            .flat_map(|sample| sample.measurements.keys())
+            .filter(|unit| {
+                PRICED_UNITS.contains(&unit.0.as_str()) || unit.0.starts_with(FEE_UNIT_PREFIX)
+            })
+            .cloned(),
+    );
+
+    let baseline_samples = baseline_samples
+        .into_iter()
+        .map(|sample| label_priced_measurements(sample, &priced_units, baseline_label, true));
+    let compared_samples = compared_samples
+        .into_iter()
+        .map(|sample| label_priced_measurements(sample, &priced_units, compared_label, false))
+        .filter(|sample| !sample.measurements.is_empty());
+
+    baseline_samples.chain(compared_samples).collect()
+}
+
+/// Returns measurements whose totals differ for any call stack between the two sample sets.
+fn differing_units(
+    baseline_samples: &[Sample],
+    compared_samples: &[Sample],
+) -> HashSet<MeasurementUnit> {
+    let baseline_totals = totals_by_call_stack(baseline_samples);
+    let compared_totals = totals_by_call_stack(compared_samples);
+
+    baseline_totals
+        .iter()
+        .filter(|(key, value)| compared_totals.get(*key).unwrap_or(&0) != *value)
+        .chain(
+            compared_totals
+                .iter()
+                .filter(|(key, value)| baseline_totals.get(*key).unwrap_or(&0) != *value),
+        )
+        .map(|((unit, _), _)| (*unit).clone())
+        .collect()
+}
+
+fn totals_by_call_stack(samples: &[Sample]) -> HashMap<(&MeasurementUnit, &[FunctionCall]), i64> {
+    let mut totals = HashMap::new();
+    for sample in samples {
+        for (unit, value) in &sample.measurements {
+            *totals
+                .entry((unit, sample.call_stack.as_slice()))
+                .or_insert(0) += value.0;
+        }
+    }
+    totals
+}
+
+fn label_priced_measurements(
+    sample: Sample,
+    priced_units: &HashSet<MeasurementUnit>,
+    label: &str,
+    keep_unpriced: bool,
+) -> Sample {
+    let measurements = sample
+        .measurements
+        .into_iter()
+        .filter_map(|(unit, value)| {
+            if priced_units.contains(&unit) {
+                Some((MeasurementUnit(format!("{}@{label}", unit.0)), value))
+            } else {
+                keep_unpriced.then_some((unit, value))
+            }
+        })
+        .collect();
+
+    Sample {
+        call_stack: sample.call_stack,
+        measurements,
+    }
+}
@@ -0,0 +1,217 @@
+//! Overrides of individual costs from versioned constants, allowing to evaluate how changing them
+//! would affect the profile without editing a whole versioned constants file.
+use crate::versioned_constants_reader::{SyscallVariant, VersionedConstants};
+use anyhow::{Result, anyhow};
+use cairo_annotations::trace_data::DeprecatedSyscallSelector;
+use std::fmt;
+
+/// Names of builtins, as they appear in resources of syscalls.
+const SYSCALL_BUILTINS: [&str; 10] = [
+    "range_check_builtin",
+    "range_check96_builtin",
+    "keccak_builtin",
+    "pedersen_builtin",
+    "bitwise_builtin",
+    "ec_op_builtin",
+    "poseidon_builtin",
+    "add_mod_builtin",
+    "mul_mod_builtin",
+    "ecdsa_builtin",
+];
+
+/// Names of builtins, as they appear in `builtin_gas_costs`.
+const GAS_COSTED_BUILTINS: [&str; 10] = [
+    "range_check",
+    "range_check96",
+    "keccak",
+    "pedersen",
+    "bitwise",
+    "ecop",
+    "poseidon",
+    "add_mod",
+    "mul_mod",
+    "ecdsa",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CostOverride {
+    /// Overrides a resource used by a syscall, e.g. `syscall:StorageWrite.n_steps=100`.
+    /// Resources of syscalls scaled with their calldata are overridden in the constant part,
+    /// unless the resource is prefixed with `calldata_factor.`.
+    Syscall {
+        selector: DeprecatedSyscallSelector,
+        calldata_factor: bool,
+        resource: SyscallResource,
+        value: usize,
+    },
+    /// Overrides the sierra gas cost of a builtin, e.g. `builtin:poseidon=500`.
+    Builtin { builtin: String, gas: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyscallResource {
+    Steps,
+    MemoryHoles,
+    Builtin(String),
+}
+
+impl fmt::Display for SyscallResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyscallResource::Steps => write!(f, "n_steps"),
+            SyscallResource::MemoryHoles => write!(f, "n_memory_holes"),
+            SyscallResource::Builtin(builtin) => write!(f, "{builtin}"),
+        }
+    }
+}
+
+impl fmt::Display for CostOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CostOverride::Syscall {
+                selector,
+                calldata_factor,
+                resource,
+                value,
+            } => {
+                let scope = if *calldata_factor {
+                    "calldata_factor."
+                } else {
+                    ""
+                };
+                write!(f, "syscall:{selector:?}.{scope}{resource}={value}")
+            }
+            CostOverride::Builtin { builtin, gas } => write!(f, "builtin:{builtin}={gas}"),
+        }
+    }
+}
+
+/// Parses an override of the form `syscall:<syscall>.[calldata_factor.]<resource>=<value>`
+/// or `builtin:<builtin>=<gas>`.
+pub fn parse_cost_override(cost_override: &str) -> Result<CostOverride, String> {
+    let invalid = |reason: String| format!("invalid cost override `{cost_override}`: {reason}");
+
+    let (target, value) = cost_override
+        .split_once('=')
+        .ok_or_else(|| invalid("expected `<target>=<value>`".to_string()))?;
+
+    if let Some(syscall) = target.strip_prefix("syscall:") {
+        let (selector, resource) = syscall.split_once('.').ok_or_else(|| {
+            invalid("expected `syscall:<syscall>.[calldata_factor.]<resource>`".to_string())
+        })?;
+        let selector = selector
+            .parse::<DeprecatedSyscallSelector>()
+            .map_err(|_| invalid(format!("unknown syscall `{selector}`")))?;
+        let (calldata_factor, resource) = match resource.strip_prefix("calldata_factor.") {
+            Some(resource) => (true, resource),
+            None => (false, resource),
+        };
+        let resource = match resource {
+            "n_steps" => SyscallResource::Steps,
+            "n_memory_holes" => SyscallResource::MemoryHoles,
+            builtin if SYSCALL_BUILTINS.contains(&builtin) => {
+                SyscallResource::Builtin(builtin.to_string())
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "unknown syscall resource `{resource}`, expected `n_steps`, `n_memory_holes` or one of: {}",
+                    SYSCALL_BUILTINS.join(", ")
+                )));
+            }
+        };
+        let value = value
+            .parse()
+            .map_err(|err| invalid(format!("invalid value `{value}`: {err}")))?;
+
+        Ok(CostOverride::Syscall {
+            selector,
+            calldata_factor,
+            resource,
+            value,
+        })
+    } else if let Some(builtin) = target.strip_prefix("builtin:") {
+        if !GAS_COSTED_BUILTINS.contains(&builtin) {
+            return Err(invalid(format!(
+                "unknown builtin `{builtin}`, expected one of: {}",
+                GAS_COSTED_BUILTINS.join(", ")
+            )));
+        }
+        let gas = value
+            .parse()
+            .map_err(|err| invalid(format!("invalid value `{value}`: {err}")))?;
+
+        Ok(CostOverride::Builtin {
+            builtin: builtin.to_string(),
+            gas,
+        })
+    } else {
+        Err(invalid(
+            "expected target prefixed with `syscall:` or `builtin:`".to_string(),
+        ))
+    }
+}
+
+/// Patches `versioned_constants` with given overrides, applied in order.
+pub fn apply_cost_overrides(
+    versioned_constants: &mut VersionedConstants,
+    cost_overrides: &[CostOverride],
+) -> Result<()> {
+    for cost_override in cost_overrides {
+        match cost_override {
+            CostOverride::Syscall {
+                selector,
+                calldata_factor,
+                resource,
+                value,
+            } => {
+                let syscall_resources = versioned_constants
+                    .os_resources
+                    .execute_syscalls
+                    .get_mut(selector)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Cannot apply cost override `{cost_override}`: versioned constants do not contain the cost of {selector:?} syscall"
+                        )
+                    })?;
+                let resources = match (syscall_resources, calldata_factor) {
+                    (SyscallVariant::Unscaled(resources), false) => resources,
+                    (SyscallVariant::Scaled(resources), false) => &mut resources.constant,
+                    (SyscallVariant::Scaled(resources), true) => &mut resources.calldata_factor,
+                    (SyscallVariant::Unscaled(_), true) => {
+                        return Err(anyhow!(
+                            "Cannot apply cost override `{cost_override}`: {selector:?} syscall is not scaled with calldata"
+                        ));
+                    }
+                };
+                match resource {
+                    SyscallResource::Steps => resources.n_steps = *value,
+                    SyscallResource::MemoryHoles => resources.n_memory_holes = *value,
+                    SyscallResource::Builtin(builtin) => {
+                        resources
+                            .builtin_instance_counter
+                            .insert(builtin.clone(), *value);
+                    }
+                }
+            }
+            CostOverride::Builtin { builtin, gas } => {
+                let costs = &mut versioned_constants.os_constants.builtin_gas_costs;
+                let cost = match builtin.as_str() {
+                    "range_check" => &mut costs.range_check,
+                    "range_check96" => &mut costs.range_check96,
+                    "keccak" => &mut costs.keccak,
+                    "pedersen" => &mut costs.pedersen,
+                    "bitwise" => &mut costs.bitwise,
+                    "ecop" => &mut costs.ecop,
+                    "poseidon" => &mut costs.poseidon,
+                    "add_mod" => &mut costs.add_mod,
+                    "mul_mod" => &mut costs.mul_mod,
+                    "ecdsa" => &mut costs.ecdsa,
+                    _ => unreachable!("builtins are validated when parsing overrides"),
+                };
+                *cost = *gas;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,193 @@
+//! Annotated CASM disassembly of a single function, showing how many times each of its
+//! instructions and sierra statements was executed in the trace.
+use crate::sierra_loader::CompiledArtifactsCache;
+use crate::trace_reader::collect_calls_with_cairo_execution_info;
+use anyhow::{Context, Result};
+use cairo_annotations::trace_data::{CallTraceV1, CasmLevelInfo};
+use cairo_lang_sierra::program::Program;
+use camino::{Utf8Path, Utf8PathBuf};
+use prettytable::{Table, format};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::ops::Range;
+
+/// Number of executions of CASM instructions keyed by their bytecode offsets.
+pub type ExecutionCounts = HashMap<usize, usize>;
+
+/// Returns execution counts of instructions of programs executed in the trace,
+/// keyed by absolute paths of the programs.
+pub fn collect_execution_counts(
+    trace: &CallTraceV1,
+    compiled_artifacts_cache: &CompiledArtifactsCache,
+) -> Result<BTreeMap<Utf8PathBuf, ExecutionCounts>> {
+    let mut calls = vec![];
+    collect_calls_with_cairo_execution_info(trace, &mut calls);
+
+    let mut execution_counts: BTreeMap<Utf8PathBuf, ExecutionCounts> = BTreeMap::new();
+    for call in calls {
+        let cairo_execution_info = call
+            .cairo_execution_info
+            .as_ref()
+            .expect("Only calls with Cairo execution info are collected");
+        let absolute_source_sierra_path = compiled_artifacts_cache
+            .resolve_sierra_path(&cairo_execution_info.source_sierra_path)?;
+        let Some(bytecode_length) = compiled_artifacts_cache
+            .get_compiled_artifacts_for_path(&absolute_source_sierra_path)
+            .casm_debug_info
+            .sierra_statement_info
+            .last()
+            .map(|statement_info| statement_info.end_offset)
+        else {
+            continue;
+        };
+
+        let program_execution_counts = execution_counts
+            .entry(absolute_source_sierra_path)
+            .or_default();
+        let casm_level_info = &cairo_execution_info.casm_level_info;
+        let real_minimal_pc = real_minimal_pc(casm_level_info);
+        for trace_entry in &casm_level_info.vm_trace {
+            // Pcs before the program belong to its header, the ones after it to its constants
+            if let Some(offset) = trace_entry.pc.checked_sub(real_minimal_pc)
+                && offset < bytecode_length
+            {
+                *program_execution_counts.entry(offset).or_default() += 1;
+            }
+        }
+    }
+
+    Ok(execution_counts)
+}
+
+/// Returns the pc at which the first instruction of the program was placed in the trace, after
+/// headers wrapping the program. Mirrors the computation done by `map_pcs_to_sierra_statement_ids`.
+fn real_minimal_pc(casm_level_info: &CasmLevelInfo) -> usize {
+    if let Some(offset) = casm_level_info.program_offset {
+        offset + 1
+    } else {
+        casm_level_info
+            .run_with_call_header
+            .then(|| casm_level_info.vm_trace.last())
+            .flatten()
+            .map_or(1, |trace_entry| trace_entry.pc + 1)
+    }
+}
+
+/// Returns indices of sierra statements of the function with the given name,
+/// or `None` if the program does not contain such a function.
+pub fn function_statements(program: &Program, function_name: &str) -> Option<Range<usize>> {
+    let function = program
+        .funcs
+        .iter()
+        .find(|function| function.id.debug_name.as_deref() == Some(function_name))?;
+    let start = function.entry_point.0;
+    let end = program
+        .funcs
+        .iter()
+        .map(|function| function.entry_point.0)
+        .filter(|&entry_point| entry_point > start)
+        .min()
+        .unwrap_or(program.statements.len());
+
+    Some(start..end)
+}
+
+/// Returns the disassembly of the function in every program in which it was executed in the trace,
+/// or `None` if there are no such programs.
+pub fn disassemble_function(
+    trace: &CallTraceV1,
+    compiled_artifacts_cache: &CompiledArtifactsCache,
+    function_name: &str,
+) -> Result<Option<String>> {
+    let mut disassembly = String::new();
+
+    for (sierra_path, execution_counts) in
+        collect_execution_counts(trace, compiled_artifacts_cache)?
+    {
+        let compiled_artifacts =
+            compiled_artifacts_cache.get_compiled_artifacts_for_path(&sierra_path);
+        let program = &compiled_artifacts.sierra_program;
+        let Some(statements) = function_statements(program, function_name) else {
+            continue;
+        };
+        let statements_info = &compiled_artifacts.casm_debug_info.sierra_statement_info;
+        let function_offsets = statements_info[statements.start].start_offset
+            ..statements_info[statements.end - 1].end_offset;
+        if !execution_counts
+            .keys()
+            .any(|offset| function_offsets.contains(offset))
+        {
+            continue;
+        }
+
+        let casm_instructions = compiled_artifacts
+            .casm_instructions
+            .as_ref()
+            .expect("CASM instructions are kept when the disassembly is requested");
+        let mut instructions = Vec::with_capacity(casm_instructions.len());
+        let mut offset = 0;
+        for instruction in casm_instructions {
+            let mut code = instruction.body.to_string();
+            if instruction.inc_ap {
+                code.push_str(", ap++");
+            }
+            instructions.push((offset, code));
+            offset += instruction.body.op_size();
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(row![r->"executions", r->"offset", "code"]);
+
+        for idx in statements {
+            let statement_info = &statements_info[idx];
+            let first_instruction =
+                instructions.partition_point(|(offset, _)| *offset < statement_info.start_offset);
+            let last_instruction =
+                instructions.partition_point(|(offset, _)| *offset < statement_info.end_offset);
+            let statement_instructions = &instructions[first_instruction..last_instruction];
+
+            // A statement is executed as many times as its first instruction
+            let statement_executions = statement_instructions
+                .first()
+                .map(|(offset, _)| {
+                    execution_counts
+                        .get(offset)
+                        .copied()
+                        .unwrap_or_default()
+                        .to_string()
+                })
+                .unwrap_or_default();
+            table.add_row(row![
+                r->statement_executions,
+                r->"",
+                l->format!("{idx}: {}", program.statements[idx])
+            ]);
+
+            for (offset, code) in statement_instructions {
+                table.add_row(row![
+                    r->execution_counts.get(offset).copied().unwrap_or_default(),
+                    r->offset,
+                    l->format!("    {code}")
+                ]);
+            }
+        }
+
+        writeln!(
+            disassembly,
+            "\nDisassembly of {function_name} from {sierra_path}\n"
+        )?;
+        write!(disassembly, "{table}")?;
+    }
+
+    Ok((!disassembly.is_empty()).then_some(disassembly))
+}
+
+pub fn save_disassembly(disassembly: &str, path: &Utf8Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .context("Failed to create parent directories for the disassembly")?;
+    }
+    fs::write(path, disassembly).with_context(|| format!("Failed to write disassembly to {path}"))
+}
@@ -0,0 +1,186 @@
+use crate::trace_reader::sample::{MeasurementUnit, MeasurementValue, Sample};
+use anyhow::{Context, Result, anyhow};
+use camino::Utf8Path;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FeeUnit {
+    /// STRK fees, in FRI (10^-18 STRK)
+    Fri,
+    /// ETH fees, in WEI (10^-18 ETH)
+    Wei,
+}
+
+impl fmt::Display for FeeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeUnit::Fri => write!(f, "fri"),
+            FeeUnit::Wei => write!(f, "wei"),
+        }
+    }
+}
+
+/// Prices of a unit of each gas kind, in `unit`.
+#[derive(Debug)]
+pub struct GasPrices {
+    pub unit: FeeUnit,
+    pub l1_gas: u128,
+    pub l1_data_gas: u128,
+    pub l2_gas: u128,
+}
+
+/// Gas prices as they appear in Starknet block headers (e.g. returned by `starknet_getBlockWithTxHashes`).
+/// Other fields of the header are ignored.
+#[derive(Debug, Deserialize)]
+struct GasPricesFile {
+    #[serde(rename = "l1_gas_price")]
+    l1: Option<ResourcePrice>,
+    #[serde(rename = "l1_data_gas_price")]
+    l1_data: Option<ResourcePrice>,
+    #[serde(rename = "l2_gas_price")]
+    l2: Option<ResourcePrice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourcePrice {
+    price_in_fri: Option<Price>,
+    price_in_wei: Option<Price>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Price {
+    Number(u128),
+    String(String),
+}
+
+impl ResourcePrice {
+    fn in_unit(&self, unit: FeeUnit) -> Result<Option<u128>> {
+        let price = match unit {
+            FeeUnit::Fri => &self.price_in_fri,
+            FeeUnit::Wei => &self.price_in_wei,
+        };
+        match price {
+            None => Ok(None),
+            Some(Price::Number(price)) => Ok(Some(*price)),
+            Some(Price::String(price)) => parse_price(price).map(Some).map_err(|err| anyhow!(err)),
+        }
+    }
+}
+
+/// Parses a decimal or `0x`-prefixed (or `0X`-prefixed) hexadecimal gas price.
+pub fn parse_price(price: &str) -> Result<u128, String> {
+    match price
+        .strip_prefix("0x")
+        .or_else(|| price.strip_prefix("0X"))
+    {
+        Some(hex) => u128::from_str_radix(hex, 16),
+        None => price.parse(),
+    }
+    .map_err(|err| format!("invalid gas price `{price}`: {err}"))
+}
+
+impl GasPrices {
+    /// Combines prices from the file at `path` with ones given explicitly, the latter taking precedence.
+    /// Prices of all gas kinds must be known.
+    pub fn new(
+        unit: FeeUnit,
+        path: Option<&Utf8Path>,
+        l1_gas: Option<u128>,
+        l1_data_gas: Option<u128>,
+        l2_gas: Option<u128>,
+    ) -> Result<Self> {
+        let file_prices = path
+            .map(|path| {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Cannot read gas prices file at {path}"))?;
+                serde_json::from_str::<GasPricesFile>(&content)
+                    .with_context(|| format!("Failed to parse gas prices file at {path}"))
+            })
+            .transpose()?;
+
+        let resolve = |explicit_price: Option<u128>,
+                       file_price: Option<&ResourcePrice>,
+                       name: &str|
+         -> Result<u128> {
+            if let Some(price) = explicit_price {
+                return Ok(price);
+            }
+            file_price
+                .map(|price| price.in_unit(unit))
+                .transpose()?
+                .flatten()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Missing {name} price in {unit}. Provide it with `--{}-price` or in the gas prices file",
+                        name.replace('_', "-")
+                    )
+                })
+        };
+
+        Ok(Self {
+            unit,
+            l1_gas: resolve(
+                l1_gas,
+                file_prices.as_ref().and_then(|f| f.l1.as_ref()),
+                "l1_gas",
+            )?,
+            l1_data_gas: resolve(
+                l1_data_gas,
+                file_prices.as_ref().and_then(|f| f.l1_data.as_ref()),
+                "l1_data_gas",
+            )?,
+            l2_gas: resolve(
+                l2_gas,
+                file_prices.as_ref().and_then(|f| f.l2.as_ref()),
+                "l2_gas",
+            )?,
+        })
+    }
+
+    pub fn fee_unit(&self) -> MeasurementUnit {
+        MeasurementUnit::from(format!("fee_{}", self.unit))
+    }
+}
+
+/// Adds the estimated fee to every sample, based on its `l1_gas`, `l1_data_gas` and `l2_gas` measurements.
+pub fn add_fee_estimations(samples: &mut [Sample], gas_prices: &GasPrices) -> Result<()> {
+    let fee_unit = gas_prices.fee_unit();
+    for sample in samples {
+        let mut fee: i128 = 0;
+        for (gas_unit, price) in [
+            ("l1_gas", gas_prices.l1_gas),
+            ("l1_data_gas", gas_prices.l1_data_gas),
+            ("l2_gas", gas_prices.l2_gas),
+        ] {
+            if let Some(MeasurementValue(amount)) = sample
+                .measurements
+                .get(&MeasurementUnit::from(gas_unit.to_string()))
+            {
+                fee = i128::try_from(price)
+                    .ok()
+                    .and_then(|price| price.checked_mul(i128::from(*amount)))
+                    .and_then(|cost| fee.checked_add(cost))
+                    .ok_or_else(|| anyhow!("Estimated fee overflowed"))?;
+            }
+        }
+
+        if fee != 0 {
+            if sample.measurements.contains_key(&fee_unit) {
+                return Err(anyhow!(
+                    "Cannot estimate fees: samples already contain `{}` measurements",
+                    fee_unit.0
+                ));
+            }
+            let fee = i64::try_from(fee)
+                .context("Estimated fee is too large to be saved in the profile")?;
+            sample
+                .measurements
+                .insert(fee_unit.clone(), MeasurementValue(fee));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Summary of libfuncs executed in the trace, grouped by their generic ids (e.g. `u256_safe_divmod`),
+//! showing which kinds of libfuncs cost the most across the whole program.
+use crate::trace_reader::function_trace_builder::cost::BuiltinCounts;
+use crate::ui;
+use crate::versioned_constants_reader::BuiltinGasCosts;
+use anyhow::{Context, Result};
+use cairo_annotations::annotations::profiler::FunctionName;
+use camino::Utf8Path;
+use prettytable::{Table, format};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::AddAssign;
+
+/// Number of functions listed as the top callers of each libfunc.
+const TOP_CALLERS_LIMIT: usize = 3;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct LibfuncUsage {
+    pub calls: usize,
+    pub steps: usize,
+    /// Includes `builtins_sierra_gas`.
+    pub sierra_gas: usize,
+    /// Sierra gas charged for builtins used by the libfunc, computed from its cost tokens.
+    pub builtins_sierra_gas: usize,
+    /// Builtins used by the libfunc, unless they are already charged in `builtins_sierra_gas`.
+    #[serde(skip_serializing_if = "BuiltinCounts::is_empty")]
+    pub builtins: BuiltinCounts,
+}
+
+impl LibfuncUsage {
+    /// Cost the libfuncs are ranked by - sierra gas if it was tracked, steps otherwise.
+    fn cost(&self) -> (usize, usize) {
+        (self.sierra_gas, self.steps)
+    }
+}
+
+impl AddAssign for LibfuncUsage {
+    fn add_assign(&mut self, rhs: Self) {
+        self.calls += rhs.calls;
+        self.steps += rhs.steps;
+        self.sierra_gas += rhs.sierra_gas;
+        self.builtins_sierra_gas += rhs.builtins_sierra_gas;
+        self.builtins += rhs.builtins;
+    }
+}
+
+/// Usage of libfuncs keyed by their generic ids and then by functions calling them.
+#[derive(Debug, Default)]
+pub struct LibfuncReport(HashMap<String, HashMap<FunctionName, LibfuncUsage>>);
+
+#[derive(Serialize)]
+struct LibfuncSummary<'a> {
+    libfunc: &'a str,
+    #[serde(flatten)]
+    usage: LibfuncUsage,
+    top_callers: Vec<CallerSummary<'a>>,
+}
+
+#[derive(Serialize)]
+struct CallerSummary<'a> {
+    function: &'a str,
+    #[serde(flatten)]
+    usage: LibfuncUsage,
+}
+
+impl LibfuncReport {
+    pub fn add(&mut self, libfunc: &str, caller: FunctionName, usage: LibfuncUsage) {
+        *self
+            .0
+            .entry(libfunc.to_string())
+            .or_default()
+            .entry(caller)
+            .or_default() += usage;
+    }
+
+    /// Adds usage from `other`, keeping builtins used by libfuncs as their counts.
+    pub fn merge(&mut self, other: &LibfuncReport) {
+        for (libfunc, callers) in &other.0 {
+            for (caller, usage) in callers {
+                self.add(libfunc, caller.clone(), *usage);
+            }
+        }
+    }
+
+    /// Adds usage from `other`, charging sierra gas for builtins used by libfuncs according to
+    /// `builtin_gas_costs`.
+    pub fn merge_priced(&mut self, other: &LibfuncReport, builtin_gas_costs: &BuiltinGasCosts) {
+        for (libfunc, callers) in &other.0 {
+            for (caller, usage) in callers {
+                let builtins_sierra_gas = usage.builtins.sierra_gas(builtin_gas_costs);
+                self.add(
+                    libfunc,
+                    caller.clone(),
+                    LibfuncUsage {
+                        sierra_gas: usage.sierra_gas + builtins_sierra_gas,
+                        builtins_sierra_gas: usage.builtins_sierra_gas + builtins_sierra_gas,
+                        builtins: BuiltinCounts::default(),
+                        ..*usage
+                    },
+                );
+            }
+        }
+    }
+
+    /// Returns summaries of libfuncs ordered from the most expensive one.
+    fn summaries(&self) -> Vec<LibfuncSummary<'_>> {
+        let mut summaries: Vec<LibfuncSummary> = self
+            .0
+            .iter()
+            .map(|(libfunc, callers)| {
+                let mut usage = LibfuncUsage::default();
+                for caller_usage in callers.values() {
+                    usage += *caller_usage;
+                }
+
+                let mut top_callers: Vec<CallerSummary> = callers
+                    .iter()
+                    .map(|(caller, usage)| CallerSummary {
+                        function: caller.0.as_str(),
+                        usage: *usage,
+                    })
+                    .collect();
+                top_callers.sort_by(|a, b| {
+                    b.usage
+                        .cost()
+                        .cmp(&a.usage.cost())
+                        .then_with(|| a.function.cmp(b.function))
+                });
+                top_callers.truncate(TOP_CALLERS_LIMIT);
+
+                LibfuncSummary {
+                    libfunc: libfunc.as_str(),
+                    usage,
+                    top_callers,
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            b.usage
+                .cost()
+                .cmp(&a.usage.cost())
+                .then_with(|| a.libfunc.cmp(b.libfunc))
+        });
+        summaries
+    }
+
+    pub fn print(&self, sierra_gas_tracking: bool) {
+        let summaries = self.summaries();
+        ui::msg(format!(
+            "\nLibfunc summary ({} libfuncs)\n",
+            summaries.len()
+        ));
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        if sierra_gas_tracking {
+            table.set_titles(row![r->"calls", r->"sierra gas", r->"builtins sierra gas", "libfunc", "top callers"]);
+        } else {
+            table.set_titles(row![r->"calls", r->"steps", "builtins", "libfunc", "top callers"]);
+        }
+
+        for summary in summaries {
+            let top_callers = summary
+                .top_callers
+                .iter()
+                .map(|caller| serde_json::to_string(caller.function).unwrap())
+                .collect::<Vec<_>>()
+                .join(", ");
+            if sierra_gas_tracking {
+                table.add_row(row![
+                    r->summary.usage.calls,
+                    r->summary.usage.sierra_gas,
+                    r->summary.usage.builtins_sierra_gas,
+                    l->summary.libfunc,
+                    l->top_callers
+                ]);
+            } else {
+                let builtins = summary
+                    .usage
+                    .builtins
+                    .iter()
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(builtin, count)| format!("{count} {builtin}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                table.add_row(row![
+                    r->summary.usage.calls,
+                    r->summary.usage.steps,
+                    l->builtins,
+                    l->summary.libfunc,
+                    l->top_callers
+                ]);
+            }
+        }
+
+        table.printstd();
+    }
+
+    pub fn save(&self, path: &Utf8Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.summaries())
+            .context("Failed to serialize libfunc report")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context("Failed to create parent directories for the libfunc report")?;
+        }
+        fs::write(path, json).with_context(|| format!("Failed to write libfunc report to {path}"))
+    }
+}
@@ -1,4 +1,7 @@
+use crate::cli::annotate::run_annotate;
 use crate::cli::build_profile::run_build_profile;
+use crate::cli::bundle::run_bundle;
+use crate::cli::constants::run_constants;
 use crate::cli::view::run_view;
 use anyhow::Result;
 use clap::Parser;
@@ -7,11 +10,19 @@ use cli::{Cli, Commands};
 #[macro_use]
 extern crate prettytable;
 
+mod annotation;
+mod bundle;
 mod cli;
+mod cost_comparison;
+mod cost_override;
+mod disassembly;
+mod fee_estimation;
+mod libfunc_report;
 mod profile_builder;
 mod profile_viewer;
 mod profiler_config;
 mod sierra_loader;
+mod trace_input;
 mod trace_reader;
 mod ui;
 mod versioned_constants_reader;
@@ -22,6 +33,12 @@ fn main() -> Result<()> {
     match cli.command {
         Some(Commands::BuildProfile(build_cli)) => run_build_profile(&build_cli),
         Some(Commands::View(view_cli)) => run_view(&view_cli),
+        Some(Commands::Bundle(bundle_cli)) => run_bundle(&bundle_cli),
+        Some(Commands::Constants(constants_cli)) => {
+            run_constants(&constants_cli);
+            Ok(())
+        }
+        Some(Commands::Annotate(annotate_cli)) => run_annotate(&annotate_cli),
         None => run_build_profile(&cli.build_profile_args.expect("Failed to parse arguments")),
     }
 }
@@ -11,7 +11,7 @@ use bytes::{Buf, BytesMut};
 use camino::Utf8PathBuf;
 use flate2::{Compression, bufread::GzEncoder};
 use prost::Message;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::{fs, io::Read};
 
 pub use perftools::profiles as pprof;
@@ -94,7 +94,9 @@ impl ProfilerContext {
                 FunctionCall::InternalFunctionCall(
                     InternalFunctionCall::NonInlined(_)
                     | InternalFunctionCall::Syscall(_)
-                    | InternalFunctionCall::Libfunc(_),
+                    | InternalFunctionCall::Libfunc(_)
+                    | InternalFunctionCall::StorageVariable(_)
+                    | InternalFunctionCall::TransactionData(_),
                 )
                 | FunctionCall::EntrypointCall(_) => {
                     if index != 0 {
@@ -118,7 +120,9 @@ impl ProfilerContext {
                     | FunctionCall::InternalFunctionCall(
                         InternalFunctionCall::NonInlined(function_name)
                         | InternalFunctionCall::Syscall(function_name)
-                        | InternalFunctionCall::Libfunc(function_name),
+                        | InternalFunctionCall::Libfunc(function_name)
+                        | InternalFunctionCall::StorageVariable(function_name)
+                        | InternalFunctionCall::TransactionData(function_name),
                     ) => {
                         let line = pprof::Line {
                             function_id: self.function_id(function_name).into(),
@@ -154,7 +158,9 @@ impl ProfilerContext {
                         | FunctionCall::InternalFunctionCall(
                             InternalFunctionCall::NonInlined(_)
                             | InternalFunctionCall::Syscall(_)
-                            | InternalFunctionCall::Libfunc(_),
+                            | InternalFunctionCall::Libfunc(_)
+                            | InternalFunctionCall::StorageVariable(_)
+                            | InternalFunctionCall::TransactionData(_),
                         ) => {
                             unreachable!(
                                 "Only first function in a function stack corresponding to a single location can be not inlined"
@@ -196,8 +202,11 @@ impl ProfilerContext {
             string_table[usize::try_from(id.0).unwrap()] = st;
         }
 
-        let functions = self.functions.into_values().collect();
-        let locations = self.locations.into_values().collect();
+        // Ids are assigned in order of appearance, so ordering by them keeps the output deterministic
+        let mut functions: Vec<pprof::Function> = self.functions.into_values().collect();
+        functions.sort_by_key(|function| function.id);
+        let mut locations: Vec<pprof::Location> = self.locations.into_values().collect();
+        locations.sort_by_key(|location| location.id);
 
         (string_table, functions, locations)
     }
@@ -228,7 +237,7 @@ fn build_value_types(
 
 fn build_samples(
     context: &mut ProfilerContext,
-    samples: &[Sample],
+    samples: &[&Sample],
     all_measurements_units: &[MeasurementUnit],
 ) -> Vec<pprof::Sample> {
     samples
@@ -256,16 +265,55 @@ fn build_samples(
 }
 
 fn collect_all_measurements_units(samples: &[Sample]) -> Vec<MeasurementUnit> {
-    let units_set: HashSet<&MeasurementUnit> =
+    let units_set: BTreeSet<&MeasurementUnit> =
         samples.iter().flat_map(|m| m.measurements.keys()).collect();
     units_set.into_iter().cloned().collect()
 }
 
-pub fn build_profile(samples: &[Sample]) -> pprof::Profile {
+/// Orders samples by their call stacks and measurements, so the profile does not depend on
+/// the order samples were collected in.
+fn sort_samples(samples: &[Sample]) -> Vec<&Sample> {
+    let mut sorted_samples: Vec<&Sample> = samples.iter().collect();
+    sorted_samples.sort_by_cached_key(|sample| {
+        let measurements: BTreeMap<&MeasurementUnit, i64> = sample
+            .measurements
+            .iter()
+            .map(|(unit, value)| (unit, value.0))
+            .collect();
+        (&sample.call_stack, measurements)
+    });
+    sorted_samples
+}
+
+/// Information about how the profile was built, saved alongside its samples.
+pub struct ProfileMetadata {
+    /// Human-readable lines describing how the profile was built.
+    pub comments: Vec<String>,
+    /// Sample shown by default by pprof, if it is present in the profile.
+    pub default_sample_type: MeasurementUnit,
+    /// Time the profile was built at, in nanoseconds since the Unix epoch.
+    pub time_nanos: i64,
+}
+
+pub fn build_profile(samples: &[Sample], metadata: &ProfileMetadata) -> pprof::Profile {
     let mut context = ProfilerContext::new();
     let all_measurements_units = collect_all_measurements_units(samples);
     let value_types = build_value_types(&all_measurements_units, &mut context);
-    let pprof_samples = build_samples(&mut context, samples, &all_measurements_units);
+    let pprof_samples = build_samples(
+        &mut context,
+        &sort_samples(samples),
+        &all_measurements_units,
+    );
+    let comment = metadata
+        .comments
+        .iter()
+        .map(|comment| context.string_id(comment).into())
+        .collect();
+    let default_sample_type = if all_measurements_units.contains(&metadata.default_sample_type) {
+        context.string_id(&metadata.default_sample_type.0).into()
+    } else {
+        0
+    };
     let (string_table, functions, locations) = context.context_data();
 
     pprof::Profile {
@@ -277,12 +325,12 @@ pub fn build_profile(samples: &[Sample]) -> pprof::Profile {
         string_table,
         drop_frames: 0,
         keep_frames: 0,
-        time_nanos: 0,
+        time_nanos: metadata.time_nanos,
         duration_nanos: 0,
         period_type: None,
         period: 0,
-        comment: vec![],
-        default_sample_type: 0,
+        comment,
+        default_sample_type,
     }
 }
 
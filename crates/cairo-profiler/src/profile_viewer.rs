@@ -1,6 +1,7 @@
-use crate::profile_builder::pprof::{Function, Location, Profile};
+use crate::profile_builder::pprof::{Function, Location, Profile, Sample};
 use crate::ui;
 use anyhow::{Context, Result};
+use cairo_annotations::trace_data::DeprecatedSyscallSelector;
 use camino::Utf8PathBuf;
 use flate2::read::GzDecoder;
 use prettytable::{Table, format};
@@ -31,17 +32,7 @@ fn get_profile_data(
         .map(|pattern| Regex::new(pattern).context("Invalid regular expression passed"))
         .transpose()?;
 
-    // Labels in string_table are prefixed with a whitespace
-    let sample_label = format!(" {sample_name}");
-
-    let sample_type_idx = profile
-        .sample_type
-        .iter()
-        .position(|sample| {
-            profile.string_table[usize::try_from(sample.unit)
-                .expect("Overflow while converting samples id to usize")]
-                == sample_label
-        })
+    let sample_type_idx = sample_type_index(profile, sample_name)
         .context("Failed to find sample in provided profile")?;
 
     let mut profile_map = HashMap::<String, FunctionProfile>::new();
@@ -137,6 +128,98 @@ fn get_profile_data(
     Ok(sorted_profile_map)
 }
 
+fn sample_type_index(profile: &Profile, sample_name: &str) -> Option<usize> {
+    // Labels in string_table are prefixed with a whitespace
+    let sample_label = format!(" {sample_name}");
+
+    profile.sample_type.iter().position(|sample| {
+        profile.string_table
+            [usize::try_from(sample.unit).expect("Overflow while converting samples id to usize")]
+            == sample_label
+    })
+}
+
+#[derive(Debug, Default)]
+struct InvocationCounts {
+    entrypoint_calls: i64,
+    syscall_usage: i64,
+    function_calls: i64,
+    sample_stacks: i64,
+}
+
+impl InvocationCounts {
+    fn count(&self) -> i64 {
+        [
+            self.entrypoint_calls,
+            self.syscall_usage,
+            self.function_calls,
+            self.sample_stacks,
+        ]
+        .into_iter()
+        .find(|&count| count != 0)
+        .unwrap_or_default()
+    }
+}
+
+/// Returns the number of invocations of each function, taken from `calls` samples for entrypoints,
+/// `syscall usage` for syscalls and `function calls` for internal functions. Functions without
+/// such samples are counted as invoked once in each sample stack they appear in.
+fn get_invocation_counts(profile: &Profile) -> HashMap<String, i64> {
+    let calls_idx = sample_type_index(profile, "calls");
+    let syscall_usage_idx = sample_type_index(profile, "syscall usage");
+    let function_calls_idx = sample_type_index(profile, "function calls");
+
+    let location_map: HashMap<u64, &Location> = profile
+        .location
+        .iter()
+        .map(|location| (location.id, location))
+        .collect();
+
+    let function_map: HashMap<u64, &Function> = profile
+        .function
+        .iter()
+        .map(|function| (function.id, function))
+        .collect();
+
+    let value = |sample: &Sample, idx: Option<usize>| idx.map_or(0, |idx| sample.value[idx]);
+
+    let mut counts = HashMap::<&str, InvocationCounts>::new();
+    for sample in &profile.sample {
+        let mut seen_in_sample = HashSet::new();
+        // Syscalls may be followed by storage variables they accessed
+        let mut syscall_found = false;
+        for (idx, &loc_id) in sample.location_id.iter().enumerate() {
+            let Some(line) = location_map.get(&loc_id).and_then(|loc| loc.line.first()) else {
+                continue;
+            };
+            let Some(function) = function_map.get(&line.function_id) else {
+                continue;
+            };
+            let function_name = profile.string_table[usize::try_from(function.name)
+                .expect("Overflow while converting function id to usize")]
+            .as_str();
+
+            let entry = counts.entry(function_name).or_default();
+            if seen_in_sample.insert(function_name) {
+                entry.sample_stacks += 1;
+            }
+            if idx == 0 {
+                entry.entrypoint_calls += value(sample, calls_idx);
+                entry.function_calls += value(sample, function_calls_idx);
+            }
+            if !syscall_found && function_name.parse::<DeprecatedSyscallSelector>().is_ok() {
+                entry.syscall_usage += value(sample, syscall_usage_idx);
+                syscall_found = true;
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(function_name, counts)| (function_name.to_string(), counts.count()))
+        .collect()
+}
+
 pub fn get_samples(profile: &Profile) -> Vec<&str> {
     profile
         .sample_type
@@ -149,11 +232,52 @@ pub fn get_samples(profile: &Profile) -> Vec<&str> {
         .collect()
 }
 
+/// Returns lines describing how the profile was built: its comments, the default sample
+/// and the time it was built at.
+pub fn get_info(profile: &Profile) -> Vec<String> {
+    let string = |id: i64| {
+        profile.string_table
+            [usize::try_from(id).expect("Overflow while converting string id to usize")]
+        .as_str()
+    };
+
+    let mut info: Vec<String> = profile
+        .comment
+        .iter()
+        .map(|&comment| string(comment).to_string())
+        .collect();
+
+    let default_sample = profile
+        .sample_type
+        .iter()
+        .find(|sample| sample.r#type == profile.default_sample_type)
+        .or_else(|| profile.sample_type.last());
+    if let Some(default_sample) = default_sample {
+        // Labels in string_table are prefixed with a whitespace
+        info.push(format!(
+            "default sample: {}",
+            string(default_sample.unit).trim_start()
+        ));
+    }
+
+    if profile.time_nanos != 0 {
+        info.push(format!(
+            "built at: {} (seconds since the Unix epoch)",
+            profile.time_nanos / 1_000_000_000
+        ));
+    }
+
+    info
+}
+
+// we only care about two decimal places, so we do not really care about potential precision loss
+#[expect(clippy::cast_precision_loss)]
 pub fn print_profile(
     profile: &Profile,
     sample: &str,
     limit: NonZeroUsize,
     hide: Option<&str>,
+    per_call: bool,
 ) -> Result<()> {
     let data =
         get_profile_data(profile, sample, hide).context("Failed to get data from profile")?;
@@ -189,17 +313,41 @@ pub fn print_profile(
 
     let mut table = Table::new();
     table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-    table.set_titles(row![r->"flat", r->"flat%", r->"sum%", r->"cum", r->"cum%", ""]);
+    if per_call {
+        table.set_titles(row![r->"flat", r->"flat%", r->"sum%", r->"cum", r->"cum%", r->"calls", r->"flat/call", r->"cum/call", ""]);
+    } else {
+        table.set_titles(row![r->"flat", r->"flat%", r->"sum%", r->"cum", r->"cum%", ""]);
+    }
+
+    let invocation_counts = if per_call {
+        get_invocation_counts(profile)
+    } else {
+        HashMap::new()
+    };
 
     for (name, profile) in sliced {
-        table.add_row(row![
+        let mut row = row![
             r->format!("{} {}", profile.flat, &sample),
             r->format!("{:.2}%", profile.flat_p),
             r->format!("{:.2}%", profile.sum_p),
             r->format!("{} {}", profile.cumulative, &sample),
             r->format!("{:.2}%", profile.cumulative_p),
-            l->serde_json::to_string(&name).unwrap()
-        ]);
+        ];
+        if per_call {
+            let calls = invocation_counts.get(name).copied().unwrap_or_default();
+            let per_call = |value: i64| {
+                if calls == 0 {
+                    "-".to_string()
+                } else {
+                    format!("{:.2} {sample}", value as f64 / calls as f64)
+                }
+            };
+            row.add_cell(cell!(r->calls));
+            row.add_cell(cell!(r->per_call(profile.flat)));
+            row.add_cell(cell!(r->per_call(profile.cumulative)));
+        }
+        row.add_cell(cell!(l->serde_json::to_string(&name).unwrap()));
+        table.add_row(row);
     }
 
     table.printstd();
@@ -1,12 +1,25 @@
 use crate::cli::build_profile::BuildProfile;
+use crate::sierra_loader::PrecomputeConfig;
 use crate::trace_reader::function_name::ExternalTool;
+use cairo_lang_starknet_classes::keccak::starknet_keccak;
+use num_bigint::BigUint;
+use std::collections::HashMap;
 
 pub struct ProfilerConfig {
     pub show_details: bool,
     pub max_function_stack_trace_depth: usize,
+    pub collapse_recursion: bool,
+    pub show_loops: bool,
     pub split_generics: bool,
+    pub split_entrypoint_invocations: bool,
     pub show_inlined_functions: bool,
     pub show_libfuncs: bool,
+    pub libfunc_report: bool,
+    pub show_storage_variables: bool,
+    /// Names of storage variables keyed by their selectors.
+    pub storage_variable_names: HashMap<BigUint, String>,
+    pub deploy_account_costs: bool,
+    pub declare_costs: bool,
     pub cairo_enable_gas: bool,
     pub external_tool: ExternalTool,
 }
@@ -20,9 +33,21 @@ impl ProfilerConfig {
         ProfilerConfig {
             show_details: cli.show_details,
             max_function_stack_trace_depth: cli.max_function_stack_trace_depth,
+            collapse_recursion: cli.collapse_recursion,
+            show_loops: cli.show_loops,
             split_generics: cli.split_generics,
+            split_entrypoint_invocations: cli.split_entrypoint_invocations,
             show_inlined_functions: cli.show_inlined_functions,
             show_libfuncs: cli.show_libfuncs,
+            libfunc_report: cli.libfunc_report || cli.libfunc_report_path.is_some(),
+            show_storage_variables: cli.show_storage_variables,
+            storage_variable_names: cli
+                .storage_variable
+                .iter()
+                .map(|name| (starknet_keccak(name.as_bytes()), name.clone()))
+                .collect(),
+            deploy_account_costs: cli.deploy_account_costs,
+            declare_costs: cli.declare_costs,
             cairo_enable_gas,
             external_tool,
         }
@@ -31,18 +56,59 @@ impl ProfilerConfig {
 
 pub struct FunctionLevelConfig {
     pub max_function_stack_trace_depth: usize,
+    pub collapse_recursion: bool,
+    pub show_loops: bool,
     pub split_generics: bool,
     pub show_inlined_functions: bool,
     pub show_libfuncs: bool,
+    pub libfunc_report: bool,
+    pub show_storage_variables: bool,
+    pub storage_variable_names: HashMap<BigUint, String>,
+    pub statement_costs: bool,
 }
 
 impl From<&ProfilerConfig> for FunctionLevelConfig {
     fn from(profiler_config: &ProfilerConfig) -> FunctionLevelConfig {
         FunctionLevelConfig {
             max_function_stack_trace_depth: profiler_config.max_function_stack_trace_depth,
+            collapse_recursion: profiler_config.collapse_recursion,
+            show_loops: profiler_config.show_loops,
             split_generics: profiler_config.split_generics,
             show_inlined_functions: profiler_config.show_inlined_functions,
             show_libfuncs: profiler_config.show_libfuncs,
+            libfunc_report: profiler_config.libfunc_report,
+            show_storage_variables: profiler_config.show_storage_variables,
+            storage_variable_names: profiler_config.storage_variable_names.clone(),
+            statement_costs: false,
+        }
+    }
+}
+
+impl FunctionLevelConfig {
+    /// Collects only costs of sierra statements. They do not depend on function call stacks,
+    /// so these are kept as shallow as possible.
+    pub fn statement_costs_only() -> FunctionLevelConfig {
+        FunctionLevelConfig {
+            max_function_stack_trace_depth: 1,
+            collapse_recursion: false,
+            show_loops: false,
+            split_generics: false,
+            show_inlined_functions: false,
+            show_libfuncs: false,
+            libfunc_report: false,
+            show_storage_variables: false,
+            storage_variable_names: HashMap::new(),
+            statement_costs: true,
+        }
+    }
+}
+
+impl From<&BuildProfile> for PrecomputeConfig {
+    fn from(cli: &BuildProfile) -> PrecomputeConfig {
+        PrecomputeConfig {
+            storage_variable_names: cli.show_storage_variables,
+            function_lines: cli.show_loops,
+            casm_instructions: cli.disassemble.is_some(),
         }
     }
 }
@@ -1,46 +1,163 @@
+use crate::sierra_loader::path_resolver::SierraPathResolver;
 use anyhow::{Context, Result, anyhow};
 use cairo_annotations::annotations::TryFromDebugInfo;
+use cairo_annotations::annotations::coverage::{
+    CodeLocation, CoverageAnnotationsV1, VersionedCoverageAnnotations,
+};
 use cairo_annotations::annotations::profiler::{
     ProfilerAnnotationsV1, VersionedProfilerAnnotations,
 };
 use cairo_annotations::trace_data::{CallTraceNode, CallTraceV1};
+use cairo_lang_casm::ap_change::ApChange;
+use cairo_lang_casm::cell_expression::{CellExpression, CellOperator};
+use cairo_lang_casm::instructions::Instruction;
+use cairo_lang_casm::operand::{DerefOrImmediate, Register};
 use cairo_lang_sierra::debug_info::DebugInfo;
-use cairo_lang_sierra::program::{Program, ProgramArtifact, VersionedProgram};
-use cairo_lang_sierra_to_casm::compiler::{CairoProgramDebugInfo, SierraToCasmConfig};
+use cairo_lang_sierra::extensions::core::{
+    CoreConcreteLibfunc, CoreLibfunc, CoreType, CoreTypeConcrete,
+};
+use cairo_lang_sierra::extensions::gas::{CostTokenMap, CostTokenType};
+use cairo_lang_sierra::program::{
+    GenStatement, GenericArg, Program, ProgramArtifact, StatementIdx, VersionedProgram,
+};
+use cairo_lang_sierra::program_registry::ProgramRegistry;
+use cairo_lang_sierra_gas::compute_precost_info;
+use cairo_lang_sierra_gas::gas_info::GasInfo;
+use cairo_lang_sierra_to_casm::circuit::CircuitsInfo;
+use cairo_lang_sierra_to_casm::compiler::{
+    CairoProgram, CairoProgramDebugInfo, SierraToCasmConfig, StatementKindDebugInfo,
+};
 use cairo_lang_sierra_to_casm::metadata::{
-    MetadataComputationConfig, calc_metadata, calc_metadata_ap_change_only,
+    Metadata, MetadataComputationConfig, calc_metadata, calc_metadata_ap_change_only,
 };
-use cairo_lang_sierra_type_size::ProgramRegistryInfo;
-use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
-use cairo_lang_starknet_classes::contract_class::ContractClass;
+use cairo_lang_sierra_to_casm::references::ReferenceExpression;
+use cairo_lang_sierra_type_size::{ProgramRegistryInfo, TypeSizeMap, get_type_size_map};
+use cairo_lang_starknet_classes::abi::Contract;
+use cairo_lang_starknet_classes::casm_contract_class::{CasmContractClass, ENTRY_POINT_COST};
+use cairo_lang_starknet_classes::compiler_version::VersionId;
+use cairo_lang_starknet_classes::contract_class::{ContractClass, ContractEntryPoints};
+use cairo_lang_starknet_classes::keccak::starknet_keccak;
 use camino::{Utf8Path, Utf8PathBuf};
-use std::collections::HashMap;
+use itertools::chain;
+use num_bigint::{BigInt, BigUint};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
+pub mod path_resolver;
+
+/// Bytecode of declared classes is charged per byte, with each felt taking a full word.
+const FELT_SIZE_IN_BYTES: usize = 32;
+
 /// Map with sierra and casm debug info needed for function level profiling.
 /// All paths in the map are absolute paths.
-pub struct CompiledArtifactsCache(HashMap<Utf8PathBuf, CompiledArtifacts>);
+pub struct CompiledArtifactsCache {
+    artifacts: HashMap<Utf8PathBuf, CompiledArtifacts>,
+    path_resolver: SierraPathResolver,
+    precompute_config: PrecomputeConfig,
+}
 
 pub struct CompiledArtifacts {
     pub sierra_program: Program,
-    pub sierra_program_info: ProgramRegistryInfo,
     pub casm_debug_info: CairoProgramDebugInfo,
     pub statements_functions_map: Option<ProfilerAnnotationsV1>,
+    /// Size of the class in bytes, as charged for `DECLARE` transactions.
+    /// Present only for contract classes.
+    pub declared_class_size: Option<usize>,
+    /// Data derived from the program once and shared by all calls executing it.
+    pub precomputed: PrecomputedProgramData,
+    /// CASM instructions of the program, without its constants. Present only if requested.
+    pub casm_instructions: Option<Vec<Instruction>>,
+}
+
+pub struct PrecomputedProgramData {
+    pub sierra_program_registry: ProgramRegistry<CoreType, CoreLibfunc>,
+    /// Present only if the program was compiled with gas enabled.
+    pub program_infos: Option<ProgramInfos>,
+    /// Generic libfunc names keyed by concrete libfunc ids.
+    pub libfunc_names: HashMap<u64, String>,
+    /// CASM sizes of sierra statements keyed by statement indices.
+    pub casm_sizes: HashMap<String, i64>,
+    /// Constants that may identify storage variables, keyed by indices of statements executed right
+    /// after them. Constants do not compile to any CASM instructions, so they never appear in
+    /// the trace themselves. Empty unless requested.
+    pub storage_address_constants: HashMap<StatementIdx, Vec<StorageAddressConstant>>,
+    /// 1-based lines of Cairo code at which functions start, keyed by indices of functions
+    /// in the program. Empty unless requested, or if the program was compiled without statements
+    /// code locations.
+    pub function_lines: HashMap<usize, usize>,
+    /// Names of storage variables keyed by their selectors, found in the ABI and debug info of
+    /// the program. Empty unless requested.
+    pub storage_variable_names: HashMap<BigUint, String>,
+    /// Range checks used by invocations of libfuncs in each of their branches, keyed by indices
+    /// of the invoking statements. Only invocations using any range checks are included.
+    pub range_check_usages: HashMap<StatementIdx, Vec<RangeCheckUsage>>,
+}
+
+/// Selects the data precomputed for programs, which is needed only by some features.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrecomputeConfig {
+    pub storage_variable_names: bool,
+    pub function_lines: bool,
+    pub casm_instructions: bool,
+}
+
+#[derive(Clone)]
+pub enum StorageAddressConstant {
+    /// Base address of a storage variable (`storage_base_address_const`), i.e. its selector.
+    BaseAddress(BigUint),
+    /// A `felt252` constant. It is the selector of a storage variable if the variable is a `Map`,
+    /// as base addresses of map entries are computed from it.
+    Felt252(BigUint),
+}
+
+/// Range checks used by an invocation of a libfunc when it takes one of its branches.
+pub struct RangeCheckUsage {
+    /// The first statement with CASM instructions executed after the branch, which identifies
+    /// the branch in the trace.
+    pub next_statement_idx: StatementIdx,
+    pub range_check: usize,
+    pub range_check96: usize,
+}
+
+pub struct ProgramInfos {
+    pub precost_info: GasInfo,
+    pub circuits_info: CircuitsInfo,
+    pub type_sizes: TypeSizeMap,
+    pub metadata: Metadata,
 }
 
 impl CompiledArtifactsCache {
-    pub fn new() -> Self {
-        Self(HashMap::new())
+    pub fn new(path_resolver: SierraPathResolver, precompute_config: PrecomputeConfig) -> Self {
+        Self {
+            artifacts: HashMap::new(),
+            path_resolver,
+            precompute_config,
+        }
+    }
+
+    /// Returns the absolute path under which artifacts of the sierra program saved in a trace are cached.
+    pub fn resolve_sierra_path(&self, sierra_path: &Utf8Path) -> Result<Utf8PathBuf> {
+        self.path_resolver.resolve(sierra_path)
     }
 
     pub fn get_compiled_artifacts_for_path(&self, path: &Utf8Path) -> &CompiledArtifacts {
-        self.0
+        self.artifacts
             .get(path)
             .unwrap_or_else(|| panic!("Compiled artifacts not found for path {path}"))
     }
 
+    /// Compiles the sierra program saved under given path and caches its artifacts,
+    /// unless it was already compiled.
+    pub fn add_sierra_program(
+        &mut self,
+        sierra_path: &Utf8Path,
+        cairo_enable_gas: bool,
+    ) -> Result<()> {
+        compile_sierra_and_add_compiled_artifacts_to_cache(sierra_path, self, cairo_enable_gas)
+    }
+
     pub fn statements_functions_maps_are_present(&self) -> bool {
-        self.0
+        self.artifacts
             .iter()
             .fold(true, |acc, (_path, compiled_artifacts)| {
                 acc && compiled_artifacts.statements_functions_map.is_some()
@@ -51,8 +168,11 @@ impl CompiledArtifactsCache {
 pub fn collect_and_compile_all_sierra_programs(
     trace: &CallTraceV1,
     cairo_enable_gas: bool,
+    path_resolver: SierraPathResolver,
+    precompute_config: PrecomputeConfig,
 ) -> Result<CompiledArtifactsCache> {
-    let mut compiled_artifacts_cache = CompiledArtifactsCache::new();
+    let mut compiled_artifacts_cache =
+        CompiledArtifactsCache::new(path_resolver, precompute_config);
     collect_compiled_artifacts(trace, &mut compiled_artifacts_cache, cairo_enable_gas)?;
 
     Ok(compiled_artifacts_cache)
@@ -80,17 +200,16 @@ fn collect_compiled_artifacts(
     Ok(())
 }
 
+#[expect(clippy::too_many_lines)]
 fn compile_sierra_and_add_compiled_artifacts_to_cache(
     sierra_path: &Utf8Path,
     compiled_artifacts_cache: &mut CompiledArtifactsCache,
     cairo_enable_gas: bool,
 ) -> Result<()> {
-    let absolute_sierra_path = sierra_path
-        .canonicalize_utf8()
-        .with_context(|| format!("Failed to canonicalize path: {sierra_path}"))?;
+    let absolute_sierra_path = compiled_artifacts_cache.resolve_sierra_path(sierra_path)?;
 
     if !compiled_artifacts_cache
-        .0
+        .artifacts
         .contains_key(&absolute_sierra_path)
     {
         let raw_sierra = fs::read_to_string(&absolute_sierra_path)?;
@@ -102,20 +221,22 @@ fn compile_sierra_and_add_compiled_artifacts_to_cache(
             let program_info = ProgramRegistryInfo::new(&extracted.program)
                 .context("Failed to create program registry info")?;
 
-            let statements_functions_map =
-                maybe_get_statements_functions_map(contract_class.sierra_program_debug_info);
+            let sierra_and_abi_size = sierra_and_abi_size(&contract_class)?;
+            let statements_functions_map = maybe_get_statements_functions_map(
+                contract_class.sierra_program_debug_info.as_ref(),
+            );
 
-            let contract_class = ContractClass {
-                // Debug info is unused in the compilation. This saves us a costly clone.
-                sierra_program_debug_info: None,
-                ..contract_class
-            };
+            // Debug info is unused in the compilation, so it is taken out instead of cloned.
+            let mut contract_class = contract_class;
+            let debug_info = contract_class.sierra_program_debug_info.take();
+            let abi = contract_class.abi.clone();
+            let entry_points_by_type = contract_class.entry_points_by_type.clone();
 
             let extracted_for_compile = contract_class
                 .extract_sierra_program(false)
                 .context("Failed to extract sierra program from contract code")?;
 
-            let (_casm_contract_class, casm_debug_info) =
+            let (casm_contract_class, casm_debug_info) =
                 CasmContractClass::from_contract_class_with_debug_info(
                     contract_class,
                     extracted_for_compile,
@@ -124,13 +245,42 @@ fn compile_sierra_and_add_compiled_artifacts_to_cache(
                 )
                 .context("Sierra -> CASM compilation failed.")?;
 
-            compiled_artifacts_cache.0.insert(
+            let precomputed = PrecomputedProgramData::new(
+                &extracted.program,
+                &program_info,
+                &casm_debug_info,
+                debug_info.as_ref(),
+                abi.as_ref(),
+                cairo_enable_gas,
+                compiled_artifacts_cache.precompute_config,
+            )?;
+            let casm_instructions = compiled_artifacts_cache
+                .precompute_config
+                .casm_instructions
+                .then(|| {
+                    compile_contract_program(
+                        &extracted.program,
+                        &program_info,
+                        &entry_points_by_type,
+                        extracted.sierra_version,
+                        &casm_contract_class,
+                    )
+                })
+                .transpose()?
+                .map(|casm| casm.instructions);
+
+            compiled_artifacts_cache.artifacts.insert(
                 absolute_sierra_path,
                 CompiledArtifacts {
                     sierra_program: extracted.program,
-                    sierra_program_info: program_info,
                     casm_debug_info,
                     statements_functions_map,
+                    declared_class_size: Some(
+                        casm_contract_class.bytecode.len() * FELT_SIZE_IN_BYTES
+                            + sierra_and_abi_size,
+                    ),
+                    precomputed,
+                    casm_instructions,
                 },
             );
 
@@ -144,37 +294,31 @@ fn compile_sierra_and_add_compiled_artifacts_to_cache(
             let program_info = ProgramRegistryInfo::new(&program)
                 .context("Failed to create program registry info")?;
 
-            let statements_functions_map = maybe_get_statements_functions_map(debug_info);
-            let metadata = if cairo_enable_gas {
-                calc_metadata(
-                    &program,
-                    &program_info,
-                    MetadataComputationConfig::default(),
-                )
-                .with_context(|| "Failed calculating Sierra variables (gas enabled).")?
-            } else {
-                calc_metadata_ap_change_only(&program, &program_info)
-                    .with_context(|| "Failed calculating Sierra variables (gas disabled).")?
-            };
+            let statements_functions_map = maybe_get_statements_functions_map(debug_info.as_ref());
+            let casm = compile_program(&program, &program_info, cairo_enable_gas)?;
 
-            let casm = cairo_lang_sierra_to_casm::compiler::compile(
+            let precomputed = PrecomputedProgramData::new(
                 &program,
                 &program_info,
-                &metadata,
-                SierraToCasmConfig {
-                    gas_usage_check: cairo_enable_gas,
-                    max_bytecode_size: usize::MAX,
-                },
-            )
-            .context("Sierra -> CASM compilation failed.")?;
+                &casm.debug_info,
+                debug_info.as_ref(),
+                None,
+                cairo_enable_gas,
+                compiled_artifacts_cache.precompute_config,
+            )?;
 
-            compiled_artifacts_cache.0.insert(
+            compiled_artifacts_cache.artifacts.insert(
                 absolute_sierra_path,
                 CompiledArtifacts {
                     sierra_program: program,
-                    sierra_program_info: program_info,
                     casm_debug_info: casm.debug_info,
                     statements_functions_map,
+                    declared_class_size: None,
+                    precomputed,
+                    casm_instructions: compiled_artifacts_cache
+                        .precompute_config
+                        .casm_instructions
+                        .then_some(casm.instructions),
                 },
             );
 
@@ -189,11 +333,534 @@ fn compile_sierra_and_add_compiled_artifacts_to_cache(
     Ok(())
 }
 
+fn compile_program(
+    program: &Program,
+    program_info: &ProgramRegistryInfo,
+    cairo_enable_gas: bool,
+) -> Result<CairoProgram> {
+    let metadata = if cairo_enable_gas {
+        calc_metadata(program, program_info, MetadataComputationConfig::default())
+            .with_context(|| "Failed calculating Sierra variables (gas enabled).")?
+    } else {
+        calc_metadata_ap_change_only(program, program_info)
+            .with_context(|| "Failed calculating Sierra variables (gas disabled).")?
+    };
+
+    cairo_lang_sierra_to_casm::compiler::compile(
+        program,
+        program_info,
+        &metadata,
+        SierraToCasmConfig {
+            gas_usage_check: cairo_enable_gas,
+            max_bytecode_size: usize::MAX,
+        },
+    )
+    .context("Sierra -> CASM compilation failed.")
+}
+
+/// Compiles the sierra program of a contract class the same way it is compiled into its
+/// `CasmContractClass`, i.e. with entrypoints charged for their fixed cost.
+/// Fails if the assembled program differs from the bytecode of `casm_contract_class`.
+fn compile_contract_program(
+    program: &Program,
+    program_info: &ProgramRegistryInfo,
+    entry_points_by_type: &ContractEntryPoints,
+    sierra_version: VersionId,
+    casm_contract_class: &CasmContractClass,
+) -> Result<CairoProgram> {
+    // Versions before 1.4.0 solved gas and ap changes with equations, instead of linearly
+    let linear_solver = sierra_version.supports(VersionId {
+        major: 1,
+        minor: 4,
+        patch: 0,
+    });
+    let metadata_computation_config = MetadataComputationConfig {
+        function_set_costs: chain!(
+            &entry_points_by_type.constructor,
+            &entry_points_by_type.external,
+            &entry_points_by_type.l1_handler,
+        )
+        .map(|entry_point| {
+            (
+                program.funcs[entry_point.function_idx].id.clone(),
+                CostTokenMap::from_iter([(CostTokenType::Const, ENTRY_POINT_COST)]),
+            )
+        })
+        .collect(),
+        linear_gas_solver: linear_solver,
+        linear_ap_change_solver: linear_solver,
+        ..MetadataComputationConfig::default()
+    };
+    let metadata = calc_metadata(program, program_info, metadata_computation_config)
+        .context("Failed calculating Sierra variables of the contract class.")?;
+
+    let casm = cairo_lang_sierra_to_casm::compiler::compile(
+        program,
+        program_info,
+        &metadata,
+        SierraToCasmConfig {
+            gas_usage_check: true,
+            max_bytecode_size: usize::MAX,
+        },
+    )
+    .context("Sierra -> CASM compilation failed.")?;
+
+    // Bytecode holds felts, so negative immediates are taken modulo the prime
+    let prime = BigInt::from(casm_contract_class.prime.clone());
+    let bytecode = casm.assemble().bytecode;
+    let bytecode_matches = bytecode.len() == casm_contract_class.bytecode.len()
+        && bytecode
+            .iter()
+            .zip(&casm_contract_class.bytecode)
+            .all(|(value, expected)| {
+                (value % &prime + &prime) % &prime == BigInt::from(expected.value.clone())
+            });
+    if !bytecode_matches {
+        return Err(anyhow!(
+            "CASM instructions recompiled from the contract class do not match its compiled bytecode"
+        ));
+    }
+
+    Ok(casm)
+}
+
+/// Returns the size of the sierra program and the ABI of the class in bytes, as charged for `DECLARE`
+/// transactions.
+fn sierra_and_abi_size(contract_class: &ContractClass) -> Result<usize> {
+    let abi_size = contract_class
+        .abi
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .context("Failed to serialize contract ABI")?
+        .map_or(0, |abi| abi.len());
+    Ok(contract_class.sierra_program.len() * FELT_SIZE_IN_BYTES + abi_size)
+}
+
+fn collect_storage_address_constants(
+    program: &Program,
+    casm_debug_info: &CairoProgramDebugInfo,
+) -> HashMap<StatementIdx, Vec<StorageAddressConstant>> {
+    let types: HashMap<_, _> = program
+        .type_declarations
+        .iter()
+        .map(|declaration| (&declaration.id, &declaration.long_id))
+        .collect();
+    let felt252_value = |args: &[GenericArg]| match args {
+        [GenericArg::Value(value)] => value.to_biguint(),
+        _ => None,
+    };
+
+    let libfunc_constants: HashMap<_, _> = program
+        .libfunc_declarations
+        .iter()
+        .filter_map(|declaration| {
+            let args = declaration.long_id.generic_args.as_slice();
+            let constant = match declaration.long_id.generic_id.0.as_str() {
+                "storage_base_address_const" => {
+                    StorageAddressConstant::BaseAddress(felt252_value(args)?)
+                }
+                "felt252_const" => StorageAddressConstant::Felt252(felt252_value(args)?),
+                "const_as_immediate" => {
+                    let [GenericArg::Type(const_type)] = args else {
+                        return None;
+                    };
+                    let const_type = types.get(const_type)?;
+                    let [GenericArg::Type(value_type), value @ ..] =
+                        const_type.generic_args.as_slice()
+                    else {
+                        return None;
+                    };
+                    if const_type.generic_id.0 != "Const"
+                        || types.get(value_type)?.generic_id.0 != "felt252"
+                    {
+                        return None;
+                    }
+                    StorageAddressConstant::Felt252(felt252_value(value)?)
+                }
+                _ => return None,
+            };
+            Some((&declaration.id, constant))
+        })
+        .collect();
+
+    let mut storage_address_constants = HashMap::new();
+    let mut pending_constants = vec![];
+    for (idx, statement) in program.statements.iter().enumerate() {
+        if let GenStatement::Invocation(invocation) = statement
+            && let Some(constant) = libfunc_constants.get(&invocation.libfunc_id)
+        {
+            pending_constants.push(constant.clone());
+        }
+
+        let has_instructions = casm_debug_info
+            .sierra_statement_info
+            .get(idx)
+            .is_some_and(|info| info.end_offset > info.start_offset);
+        if has_instructions && !pending_constants.is_empty() {
+            storage_address_constants
+                .insert(StatementIdx(idx), std::mem::take(&mut pending_constants));
+        }
+    }
+    storage_address_constants
+}
+
+/// Finds range checks used by invocations from the offsets by which they advance the range check
+/// pointers passed to them. Pointers which cannot be followed through an invocation are assumed
+/// not to be advanced. Range checks used by called functions and syscalls are not included.
+fn collect_range_check_usages(
+    program: &Program,
+    sierra_program_registry: &ProgramRegistry<CoreType, CoreLibfunc>,
+    casm_debug_info: &CairoProgramDebugInfo,
+) -> HashMap<StatementIdx, Vec<RangeCheckUsage>> {
+    let mut range_check_usages = HashMap::new();
+    for (idx, (statement, debug_info)) in program
+        .statements
+        .iter()
+        .zip(&casm_debug_info.sierra_statement_info)
+        .enumerate()
+    {
+        let (
+            GenStatement::Invocation(invocation),
+            StatementKindDebugInfo::Invoke(invoke_debug_info),
+        ) = (statement, &debug_info.additional_kind_info)
+        else {
+            continue;
+        };
+        if !matches!(
+            sierra_program_registry.get_libfunc(&invocation.libfunc_id),
+            Ok(libfunc) if !matches!(
+                libfunc,
+                CoreConcreteLibfunc::FunctionCall(_) | CoreConcreteLibfunc::Starknet(_)
+            )
+        ) {
+            continue;
+        }
+
+        let idx = StatementIdx(idx);
+        let usages: Vec<_> = invocation
+            .branches
+            .iter()
+            .zip(&invoke_debug_info.result_branch_changes)
+            .map(|(branch, branch_changes)| {
+                let mut usage = RangeCheckUsage {
+                    next_statement_idx: first_statement_with_instructions(
+                        program,
+                        casm_debug_info,
+                        idx.next(branch.target),
+                    ),
+                    range_check: 0,
+                    range_check96: 0,
+                };
+                for input in &invoke_debug_info.ref_values {
+                    let counter = match sierra_program_registry.get_type(&input.ty) {
+                        Ok(CoreTypeConcrete::RangeCheck(_)) => &mut usage.range_check,
+                        Ok(CoreTypeConcrete::RangeCheck96(_)) => &mut usage.range_check96,
+                        _ => continue,
+                    };
+                    if let Some(output) = branch_changes
+                        .refs
+                        .iter()
+                        .find(|output| output.ty == input.ty)
+                    {
+                        *counter += pointer_advance(
+                            &input.expression,
+                            &output.expression,
+                            &branch_changes.ap_change,
+                        )
+                        .unwrap_or_default();
+                    }
+                }
+                usage
+            })
+            .collect();
+
+        if usages
+            .iter()
+            .any(|usage| usage.range_check > 0 || usage.range_check96 > 0)
+        {
+            range_check_usages.insert(idx, usages);
+        }
+    }
+    range_check_usages
+}
+
+/// Returns the first statement with CASM instructions executed from `idx` on. Statements without
+/// them never appear in the trace.
+fn first_statement_with_instructions(
+    program: &Program,
+    casm_debug_info: &CairoProgramDebugInfo,
+    mut idx: StatementIdx,
+) -> StatementIdx {
+    for _ in 0..program.statements.len() {
+        let has_instructions = casm_debug_info
+            .sierra_statement_info
+            .get(idx.0)
+            .is_none_or(|info| info.end_offset > info.start_offset);
+        match program.statements.get(idx.0) {
+            Some(GenStatement::Invocation(invocation))
+                if !has_instructions && invocation.branches.len() == 1 =>
+            {
+                idx = idx.next(invocation.branches[0].target);
+            }
+            _ => break,
+        }
+    }
+    idx
+}
+
+/// Returns by how much the pointer referenced by `output` is advanced from the one referenced
+/// by `input`, if both are offsets from the same cell. Cells relative to `ap` are moved by
+/// `ap_change` of the invocation.
+fn pointer_advance(
+    input: &ReferenceExpression,
+    output: &ReferenceExpression,
+    ap_change: &ApChange,
+) -> Option<usize> {
+    let offset_from_cell = |expression: &ReferenceExpression| match expression.cells.as_slice() {
+        [CellExpression::Deref(cell)] => Some((*cell, 0)),
+        [
+            CellExpression::BinOp {
+                op: CellOperator::Add,
+                a,
+                b: DerefOrImmediate::Immediate(offset),
+            },
+        ] => Some((*a, i128::try_from(&offset.value).ok()?)),
+        _ => None,
+    };
+    let (mut input_cell, input_offset) = offset_from_cell(input)?;
+    let (output_cell, output_offset) = offset_from_cell(output)?;
+    if input_cell.register == Register::AP {
+        let ApChange::Known(ap_change) = ap_change else {
+            return None;
+        };
+        input_cell.offset = input_cell
+            .offset
+            .checked_sub(i16::try_from(*ap_change).ok()?)?;
+    }
+    (input_cell == output_cell)
+        .then(|| usize::try_from(output_offset - input_offset).ok())
+        .flatten()
+}
+
 fn maybe_get_statements_functions_map(
-    maybe_sierra_program_debug_info: Option<DebugInfo>,
+    maybe_sierra_program_debug_info: Option<&DebugInfo>,
 ) -> Option<ProfilerAnnotationsV1> {
     let VersionedProfilerAnnotations::V1(annotations) =
-        VersionedProfilerAnnotations::try_from_debug_info(&maybe_sierra_program_debug_info?)
-            .ok()?;
+        VersionedProfilerAnnotations::try_from_debug_info(maybe_sierra_program_debug_info?).ok()?;
     Some(annotations)
 }
+
+fn maybe_get_statements_code_locations(
+    maybe_sierra_program_debug_info: Option<&DebugInfo>,
+) -> Option<CoverageAnnotationsV1> {
+    let VersionedCoverageAnnotations::V1(annotations) =
+        VersionedCoverageAnnotations::try_from_debug_info(maybe_sierra_program_debug_info?).ok()?;
+    Some(annotations)
+}
+
+/// Returns the lowest line of Cairo code that statements of each function were generated from.
+/// Lines of inlined functions are skipped, as they may come from other places in the code.
+fn collect_function_lines(
+    program: &Program,
+    statements_code_locations: Option<&CoverageAnnotationsV1>,
+) -> HashMap<usize, usize> {
+    let Some(statements_code_locations) = statements_code_locations else {
+        return HashMap::new();
+    };
+
+    program
+        .funcs
+        .iter()
+        .enumerate()
+        .filter_map(|(function_idx, function)| {
+            let end = program
+                .funcs
+                .get(function_idx + 1)
+                .map_or(program.statements.len(), |next| next.entry_point.0);
+            (function.entry_point.0..end)
+                .filter_map(|idx| {
+                    // Locations are ordered from the most nested inlined function.
+                    let CodeLocation(_, span, _) = statements_code_locations
+                        .statements_code_locations
+                        .get(&StatementIdx(idx))?
+                        .last()?;
+                    Some(span.start.line.0 + 1)
+                })
+                .min()
+                .map(|line| (function_idx, line))
+        })
+        .collect()
+}
+
+/// Returns names of storage variables keyed by their selectors. Storage variables are not listed
+/// in compiled artifacts, so their names are looked for among identifiers in the ABI and debug info
+/// of the program, by matching their selectors with the storage address constants. Identifiers
+/// are also tried without `get_` and `set_` prefixes and trailing underscores, as getters, setters
+/// and their arguments are commonly named after the variables they access. The names are a guess -
+/// an unrelated identifier matching the selector of a variable is taken as its name as well.
+fn collect_storage_variable_names(
+    storage_address_constants: &HashMap<StatementIdx, Vec<StorageAddressConstant>>,
+    debug_info: Option<&DebugInfo>,
+    abi: Option<&Contract>,
+) -> HashMap<BigUint, String> {
+    let addresses: HashSet<&BigUint> = storage_address_constants
+        .values()
+        .flatten()
+        .map(|constant| match constant {
+            StorageAddressConstant::BaseAddress(address)
+            | StorageAddressConstant::Felt252(address) => address,
+        })
+        .collect();
+
+    let mut names = vec![];
+    if let Some(debug_info) = debug_info {
+        names.extend(debug_info.type_names.values().map(ToString::to_string));
+        names.extend(debug_info.libfunc_names.values().map(ToString::to_string));
+        names.extend(debug_info.user_func_names.values().map(ToString::to_string));
+    }
+    if let Some(abi) = abi.and_then(|abi| serde_json::to_value(abi).ok()) {
+        collect_json_strings(&abi, &mut names);
+    }
+
+    names
+        .iter()
+        .flat_map(|name| name.split(|char: char| !char.is_alphanumeric() && char != '_'))
+        .flat_map(|identifier| {
+            [
+                Some(identifier),
+                identifier.strip_prefix("get_"),
+                identifier.strip_prefix("set_"),
+                identifier.strip_suffix('_'),
+            ]
+        })
+        .flatten()
+        .filter(|identifier| {
+            identifier
+                .chars()
+                .next()
+                .is_some_and(|char| char.is_alphabetic() || char == '_')
+        })
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|identifier| {
+            (
+                starknet_keccak(identifier.as_bytes()),
+                identifier.to_string(),
+            )
+        })
+        .filter(|(selector, _)| addresses.contains(selector))
+        .collect()
+}
+
+fn collect_json_strings(value: &serde_json::Value, strings: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(string) => strings.push(string.clone()),
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_json_strings(value, strings);
+            }
+        }
+        serde_json::Value::Object(values) => {
+            for value in values.values() {
+                collect_json_strings(value, strings);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl PrecomputedProgramData {
+    fn new(
+        program: &Program,
+        program_info: &ProgramRegistryInfo,
+        casm_debug_info: &CairoProgramDebugInfo,
+        debug_info: Option<&DebugInfo>,
+        abi: Option<&Contract>,
+        cairo_enable_gas: bool,
+        config: PrecomputeConfig,
+    ) -> Result<Self> {
+        let sierra_program_registry = ProgramRegistry::<CoreType, CoreLibfunc>::new(program)
+            .context("Failed to create sierra program registry")?;
+        let program_infos = cairo_enable_gas
+            .then(|| compute_program_infos(program, program_info, &sierra_program_registry))
+            .transpose()?;
+
+        let libfunc_names = program
+            .libfunc_declarations
+            .iter()
+            .map(|declaration| {
+                (
+                    declaration.id.id,
+                    declaration.long_id.generic_id.0.clone().to_string(),
+                )
+            })
+            .collect();
+
+        let mut casm_sizes: HashMap<String, i64> = HashMap::new();
+        for entry in &casm_debug_info.sierra_statement_info {
+            *casm_sizes
+                .entry(entry.instruction_idx.to_string())
+                .or_default() += i64::try_from(entry.end_offset - entry.start_offset)
+                .context("Failed to convert casm size to i64")?;
+        }
+
+        let storage_address_constants = if config.storage_variable_names {
+            collect_storage_address_constants(program, casm_debug_info)
+        } else {
+            HashMap::new()
+        };
+        let storage_variable_names = if config.storage_variable_names {
+            collect_storage_variable_names(&storage_address_constants, debug_info, abi)
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            program_infos,
+            libfunc_names,
+            casm_sizes,
+            storage_address_constants,
+            range_check_usages: collect_range_check_usages(
+                program,
+                &sierra_program_registry,
+                casm_debug_info,
+            ),
+            sierra_program_registry,
+            function_lines: if config.function_lines {
+                collect_function_lines(
+                    program,
+                    maybe_get_statements_code_locations(debug_info).as_ref(),
+                )
+            } else {
+                HashMap::new()
+            },
+            storage_variable_names,
+        })
+    }
+}
+
+fn compute_program_infos(
+    program: &Program,
+    program_info: &ProgramRegistryInfo,
+    sierra_program_registry: &ProgramRegistry<CoreType, CoreLibfunc>,
+) -> Result<ProgramInfos> {
+    let precost_info =
+        compute_precost_info(program, program_info).context("Failed to compute pre-cost info")?;
+    let circuits_info = CircuitsInfo::new(
+        sierra_program_registry,
+        program.type_declarations.iter().map(|td| &td.id),
+    )
+    .context("Failed to compute circuits info")?;
+    let type_sizes = get_type_size_map(program, sierra_program_registry)
+        .context("Failed to get type-size map")?;
+    let metadata = calc_metadata(program, program_info, MetadataComputationConfig::default())
+        .context("Failed to compute metadata")?;
+
+    Ok(ProgramInfos {
+        precost_info,
+        circuits_info,
+        type_sizes,
+        metadata,
+    })
+}
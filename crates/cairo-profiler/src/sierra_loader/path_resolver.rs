@@ -0,0 +1,91 @@
+use anyhow::{Result, anyhow};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::str::FromStr;
+
+/// Replacement of a path prefix, given as `FROM=TO`.
+#[derive(Clone, Debug)]
+pub struct PathPrefixRemap {
+    pub from: Utf8PathBuf,
+    pub to: Utf8PathBuf,
+}
+
+impl FromStr for PathPrefixRemap {
+    type Err = String;
+
+    fn from_str(remap: &str) -> Result<Self, Self::Err> {
+        let (from, to) = remap
+            .split_once('=')
+            .ok_or_else(|| format!("expected `FROM=TO`, got `{remap}`"))?;
+        if from.is_empty() {
+            return Err(format!("the prefix to remap cannot be empty in `{remap}`"));
+        }
+        Ok(Self {
+            from: from.into(),
+            to: to.into(),
+        })
+    }
+}
+
+/// Resolves `source_sierra_path`s saved in traces to the paths of artifacts on this machine.
+///
+/// Traces contain paths from the machine that generated them (e.g. CI or a Docker container),
+/// which usually do not exist locally.
+#[derive(Clone, Debug, Default)]
+pub struct SierraPathResolver {
+    remaps: Vec<PathPrefixRemap>,
+    search_dirs: Vec<Utf8PathBuf>,
+}
+
+impl SierraPathResolver {
+    pub fn new(remaps: Vec<PathPrefixRemap>, search_dirs: Vec<Utf8PathBuf>) -> Self {
+        Self {
+            remaps,
+            search_dirs,
+        }
+    }
+
+    /// Returns the absolute path of the artifact.
+    /// The path is first remapped (if several prefixes match, the last one given wins). If it
+    /// does not exist, the artifact is looked up by its file name in search directories, in order.
+    pub fn resolve(&self, sierra_path: &Utf8Path) -> Result<Utf8PathBuf> {
+        let remapped_path = self.remap(sierra_path);
+        if let Ok(absolute_path) = remapped_path.canonicalize_utf8() {
+            return Ok(absolute_path);
+        }
+
+        if let Some(file_name) = sierra_path.file_name() {
+            for search_dir in &self.search_dirs {
+                if let Ok(absolute_path) = search_dir.join(file_name).canonicalize_utf8() {
+                    return Ok(absolute_path);
+                }
+            }
+        }
+
+        let remapped_note = if remapped_path == sierra_path {
+            String::new()
+        } else {
+            format!(" (remapped to {remapped_path})")
+        };
+        let hint = if self.remaps.is_empty() && self.search_dirs.is_empty() {
+            "\nIf the trace was generated on another machine, \
+             use `--remap-path-prefix` or `--sierra-search-dir` to locate the artifacts"
+        } else {
+            ""
+        };
+        Err(anyhow!(
+            "Failed to canonicalize path: {sierra_path}{remapped_note}{hint}"
+        ))
+    }
+
+    fn remap(&self, path: &Utf8Path) -> Utf8PathBuf {
+        self.remaps
+            .iter()
+            .rev()
+            .find_map(|remap| {
+                path.strip_prefix(&remap.from)
+                    .ok()
+                    .map(|suffix| remap.to.join(suffix))
+            })
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+}
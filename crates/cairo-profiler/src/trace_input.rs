@@ -0,0 +1,93 @@
+use crate::bundle::{ExtractedBundle, TAR_HEADER_SIZE, is_bundle};
+use crate::sierra_loader::path_resolver::{PathPrefixRemap, SierraPathResolver};
+use anyhow::{Context, Result};
+use cairo_annotations::trace_data::{CallTraceV1, VersionedCallTrace};
+use camino::{Utf8Path, Utf8PathBuf};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+
+/// Path that makes the trace be read from the standard input.
+pub const STDIN_PATH: &str = "-";
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+pub struct TraceInput {
+    pub reader: Box<dyn Read>,
+    /// Set if the input is a bundle created with `cairo-profiler bundle`.
+    pub bundle: Option<ExtractedBundle>,
+}
+
+impl TraceInput {
+    /// Returns the resolver of sierra paths referenced by the trace.
+    pub fn path_resolver(
+        &self,
+        remap_path_prefix: &[PathPrefixRemap],
+        sierra_search_dir: &[Utf8PathBuf],
+    ) -> Result<SierraPathResolver> {
+        // Remaps given by the user come last, so they take precedence over the one of the bundle.
+        let mut remaps = vec![];
+        if let Some(bundle) = &self.bundle {
+            remaps.push(bundle.path_prefix_remap()?);
+        }
+        remaps.extend(remap_path_prefix.iter().cloned());
+        Ok(SierraPathResolver::new(remaps, sierra_search_dir.to_vec()))
+    }
+}
+
+/// Opens the trace at `path` (or the standard input for `-`), transparently decompressing
+/// gzip and zstd data. Compression is detected by magic bytes, not by the file extension.
+/// Bundles are extracted to a temporary directory and the trace they contain is opened.
+pub fn open_trace(path: &Utf8Path) -> Result<TraceInput> {
+    let reader: Box<dyn Read> = if path == STDIN_PATH {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(path).with_context(|| format!("Failed to open {path}"))?)
+    };
+
+    let (magic, reader) =
+        peek(reader, ZSTD_MAGIC.len()).with_context(|| format!("Failed to read {path}"))?;
+    let reader: Box<dyn Read> = if magic.starts_with(&GZIP_MAGIC) {
+        Box::new(MultiGzDecoder::new(reader))
+    } else if magic.starts_with(&ZSTD_MAGIC) {
+        Box::new(zstd::Decoder::new(reader).context("Failed to initialize zstd decoder")?)
+    } else {
+        reader
+    };
+
+    let (header, reader) =
+        peek(reader, TAR_HEADER_SIZE).with_context(|| format!("Failed to read {path}"))?;
+    if is_bundle(&header) {
+        let bundle = ExtractedBundle::extract(reader)?;
+        let reader = Box::new(File::open(bundle.trace_path()?)?);
+        return Ok(TraceInput {
+            reader,
+            bundle: Some(bundle),
+        });
+    }
+
+    Ok(TraceInput {
+        reader,
+        bundle: None,
+    })
+}
+
+/// Reads the whole trace from `reader` and deserializes it.
+pub fn read_call_trace(mut reader: Box<dyn Read>) -> Result<CallTraceV1> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .context("Failed to read call trace from a file")?;
+    let VersionedCallTrace::V1(trace) =
+        serde_json::from_str(&data).context("Failed to deserialize call trace")?;
+    Ok(trace)
+}
+
+/// Reads up to `len` bytes from the start of `reader`, returning them and a reader of the whole input.
+fn peek(mut reader: Box<dyn Read>, len: usize) -> Result<(Vec<u8>, Box<dyn Read>)> {
+    let mut start = Vec::with_capacity(len);
+    reader.by_ref().take(len as u64).read_to_end(&mut start)?;
+    let reader = Cursor::new(start.clone()).chain(reader);
+    Ok((start, Box::new(reader)))
+}
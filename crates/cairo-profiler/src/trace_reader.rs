@@ -1,15 +1,20 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use itertools::chain;
 use std::collections::HashMap;
 
+use crate::libfunc_report::LibfuncReport;
 use crate::profiler_config::{FunctionLevelConfig, ProfilerConfig};
 use crate::sierra_loader::CompiledArtifactsCache;
 use crate::trace_reader::function_name::FunctionNameExt;
-use crate::trace_reader::function_trace_builder::collect_function_level_profiling_info;
+use crate::trace_reader::function_trace_builder::{
+    FunctionLevelProfilingInfo, collect_function_level_profiling_info,
+};
 use crate::ui;
 use cairo_annotations::annotations::profiler::FunctionName;
 
 use crate::trace_reader::sample::{FunctionCall, InternalFunctionCall, Sample};
+use crate::trace_reader::state_diff::ModifiedContracts;
+use crate::trace_reader::transaction_costs::{DeclaredClasses, deploy_account_sample};
 
 use crate::trace_reader::function_trace_builder::stack_trace::map_syscall_trace_to_sample;
 use crate::versioned_constants_reader::VersionedConstants;
@@ -18,11 +23,16 @@ use cairo_annotations::trace_data::{
     EntryPointType, ExecutionResources, SyscallUsage, VmExecutionResources,
 };
 use indoc::formatdoc;
+use rayon::prelude::*;
 use std::collections::VecDeque;
 
 pub mod function_name;
-mod function_trace_builder;
+pub mod function_trace_builder;
+mod l2_l1_messages;
 pub mod sample;
+mod state_diff;
+pub mod streaming;
+mod transaction_costs;
 
 pub trait ResourcesOperations {
     fn add_resources(&mut self, rhs: &Self);
@@ -104,25 +114,39 @@ impl ResourcesOperations for VmExecutionResources {
     }
 }
 
-pub fn collect_samples_from_trace(
-    trace: &CallTraceV1,
-    compiled_artifacts_cache: &CompiledArtifactsCache,
-    profiler_config: &ProfilerConfig,
+/// A trace which calls were profiled on function level.
+pub struct ProfiledTrace {
+    pub trace: CallTraceV1,
+    pub profiler_config: ProfilerConfig,
+    pub compiled_artifacts_cache: CompiledArtifactsCache,
+    /// Ordered the same way as calls are visited during samples collection.
+    pub function_level_profiling_infos: Vec<FunctionLevelProfilingInfo>,
+}
+
+/// Collects samples from a trace, which calls were already profiled on function level,
+/// pricing them under `versioned_constants`. The same trace can be priced multiple times.
+pub fn collect_samples_from_profiled_trace(
+    profiled_trace: &ProfiledTrace,
     versioned_constants: &VersionedConstants,
 ) -> Result<Vec<Sample>> {
+    let ProfiledTrace {
+        trace,
+        profiler_config,
+        compiled_artifacts_cache,
+        function_level_profiling_infos,
+    } = profiled_trace;
     let mut samples = vec![];
     let mut current_entrypoint_call_stack = vec![];
-    let sierra_gas_tracking: bool = trace.cumulative_resources.gas_consumed.unwrap_or_default() > 0;
-
-    if sierra_gas_tracking && profiler_config.cairo_enable_gas {
-        verify_trace_data_for_l2_gas(trace);
-    }
+    let sierra_gas_tracking = is_sierra_gas_tracked(trace);
 
     collect_samples(
         &mut samples,
         &mut current_entrypoint_call_stack,
         trace,
-        compiled_artifacts_cache,
+        &mut function_level_profiling_infos.iter(),
+        &mut ModifiedContracts::default(),
+        &mut DeclaredClasses::new(compiled_artifacts_cache),
+        &mut HashMap::new(),
         profiler_config,
         versioned_constants,
         sierra_gas_tracking,
@@ -132,12 +156,179 @@ pub fn collect_samples_from_trace(
     Ok(samples)
 }
 
+/// Warns about data of the trace the profile cannot be built from accurately.
+pub fn warn_about_inaccurate_data(profiled_trace: &ProfiledTrace) {
+    let ProfiledTrace {
+        trace,
+        profiler_config,
+        function_level_profiling_infos,
+        ..
+    } = profiled_trace;
+    if is_sierra_gas_tracked(trace) && profiler_config.cairo_enable_gas {
+        verify_trace_data_for_l2_gas(trace);
+    }
+
+    if function_level_profiling_infos
+        .iter()
+        .any(|info| info.call_stacks_truncated)
+    {
+        ui::warn(format!(
+            "Some function call stacks were deeper than {} functions and were truncated - costs of \
+             the deepest functions are attributed to their callers. Consider increasing \
+             `--max-function-stack-trace-depth`.",
+            profiler_config.max_function_stack_trace_depth
+        ));
+    }
+
+    if function_level_profiling_infos
+        .iter()
+        .any(|info| info.vm_resources_exceeded_trace)
+    {
+        ui::warn(
+            "Builtins or memory holes estimated for functions of some calls exceeded the ones \
+             reported in the trace - the profile reports more of them than the trace does.",
+        );
+    }
+}
+
+/// Returns usage of libfuncs collected by function level profiling. If sierra gas is tracked,
+/// sierra gas of builtins is charged according to `versioned_constants`, otherwise the builtins
+/// are left as their counts.
+pub fn collect_libfunc_report(
+    function_level_profiling_infos: &[FunctionLevelProfilingInfo],
+    versioned_constants: &VersionedConstants,
+    sierra_gas_tracking: bool,
+) -> LibfuncReport {
+    let mut libfunc_report = LibfuncReport::default();
+    for info in function_level_profiling_infos {
+        if sierra_gas_tracking {
+            libfunc_report.merge_priced(
+                &info.libfunc_report,
+                &versioned_constants.os_constants.builtin_gas_costs,
+            );
+        } else {
+            libfunc_report.merge(&info.libfunc_report);
+        }
+    }
+    libfunc_report
+}
+
+/// Returns whether the trace was generated with sierra gas tracking, rather than Cairo steps.
+pub fn is_sierra_gas_tracked(trace: &CallTraceV1) -> bool {
+    trace.cumulative_resources.gas_consumed.unwrap_or_default() > 0
+}
+
+/// Runs function level profiling of every call with Cairo execution info in the trace.
+/// Calls are profiled independently of each other, so they are processed in parallel.
+/// The resulting infos are ordered the same way `collect_samples` visits the calls
+/// (depth-first, parent before its nested calls).
+pub fn collect_function_level_profiling_infos(
+    trace: &CallTraceV1,
+    compiled_artifacts_cache: &CompiledArtifactsCache,
+    profiler_config: &ProfilerConfig,
+) -> Result<Vec<FunctionLevelProfilingInfo>> {
+    let sierra_gas_tracking = is_sierra_gas_tracked(trace);
+    let mut calls = vec![];
+    collect_calls_with_cairo_execution_info(trace, &mut calls);
+
+    calls
+        .into_par_iter()
+        .map(|call| {
+            profile_call_functions(
+                call,
+                compiled_artifacts_cache,
+                profiler_config,
+                sierra_gas_tracking,
+            )
+        })
+        .collect()
+}
+
+pub fn collect_calls_with_cairo_execution_info<'a>(
+    trace: &'a CallTraceV1,
+    calls: &mut Vec<&'a CallTraceV1>,
+) {
+    if trace.cairo_execution_info.is_some() {
+        calls.push(trace);
+    }
+
+    for sub_trace_node in &trace.nested_calls {
+        if let CallTraceNode::EntryPointCall(sub_trace) = sub_trace_node {
+            collect_calls_with_cairo_execution_info(sub_trace, calls);
+        }
+    }
+}
+
+fn profile_call_functions(
+    trace: &CallTraceV1,
+    compiled_artifacts_cache: &CompiledArtifactsCache,
+    profiler_config: &ProfilerConfig,
+    sierra_gas_tracking: bool,
+) -> Result<FunctionLevelProfilingInfo> {
+    let cairo_execution_info = trace
+        .cairo_execution_info
+        .as_ref()
+        .expect("Only calls with Cairo execution info can be profiled on function level");
+    let absolute_source_sierra_path =
+        compiled_artifacts_cache.resolve_sierra_path(&cairo_execution_info.source_sierra_path)?;
+
+    let compiled_artifacts =
+        compiled_artifacts_cache.get_compiled_artifacts_for_path(&absolute_source_sierra_path);
+
+    let calldata_lengths = trace
+        .nested_calls
+        .iter()
+        .filter_map(|node| match node {
+            CallTraceNode::EntryPointCall(sub_trace) => sub_trace.entry_point.calldata_len,
+            CallTraceNode::DeployWithoutConstructor => None,
+        })
+        .collect();
+
+    let function_name = FunctionName::from_entry_point_params(
+        trace.entry_point.contract_name.clone(),
+        trace.entry_point.function_name.clone(),
+        trace.entry_point.contract_address.clone(),
+        trace.entry_point.entry_point_selector.clone(),
+        profiler_config.show_details,
+        profiler_config.external_tool,
+    );
+    let in_transaction = !is_transaction_entrypoint(&function_name);
+
+    let mut function_level_profiling_info = collect_function_level_profiling_info(
+        compiled_artifacts,
+        &cairo_execution_info.casm_level_info,
+        &FunctionLevelConfig::from(profiler_config),
+        sierra_gas_tracking,
+        calldata_lengths,
+        in_transaction,
+        &mut VecDeque::from(trace.entry_point.events_summary.clone().unwrap_or_default()),
+        &mut VecDeque::from(trace.used_l1_resources.l2_l1_message_sizes.clone()),
+        profiler_config.cairo_enable_gas,
+    );
+    function_level_profiling_info.check_vm_resources_against_trace(&entrypoint_vm_resources(trace));
+    Ok(function_level_profiling_info)
+}
+
+/// Returns VM resources used by the call itself, excluding its nested calls.
+fn entrypoint_vm_resources(trace: &CallTraceV1) -> VmExecutionResources {
+    let mut vm_resources = trace.cumulative_resources.vm_resources.clone();
+    for sub_trace_node in &trace.nested_calls {
+        if let CallTraceNode::EntryPointCall(sub_trace) = sub_trace_node {
+            vm_resources.sub_resources(&sub_trace.cumulative_resources.vm_resources);
+        }
+    }
+    vm_resources
+}
+
 #[expect(clippy::too_many_lines, clippy::too_many_arguments)]
-fn collect_samples<'a>(
+fn collect_samples<'a, 'i>(
     samples: &mut Vec<Sample>,
     current_entrypoint_call_stack: &mut Vec<FunctionCall>,
     trace: &'a CallTraceV1,
-    compiled_artifacts_cache: &CompiledArtifactsCache,
+    function_level_profiling_infos: &mut impl Iterator<Item = &'i FunctionLevelProfilingInfo>,
+    modified_contracts: &mut ModifiedContracts<'a>,
+    declared_classes: &mut DeclaredClasses,
+    entrypoint_invocations: &mut HashMap<FunctionName, usize>,
     profiler_config: &ProfilerConfig,
     versioned_constants: &VersionedConstants,
     sierra_gas_tracking: bool,
@@ -151,51 +342,31 @@ fn collect_samples<'a>(
         profiler_config.show_details,
         profiler_config.external_tool,
     );
-    current_entrypoint_call_stack.push(FunctionCall::EntrypointCall(function_name.clone()));
+    let entrypoint_frame_name = if profiler_config.split_entrypoint_invocations {
+        let invocation = entrypoint_invocations
+            .entry(function_name.clone())
+            .or_default();
+        *invocation += 1;
+        with_invocation_number(&function_name, *invocation)
+    } else {
+        function_name.clone()
+    };
+    current_entrypoint_call_stack.push(FunctionCall::EntrypointCall(entrypoint_frame_name));
     let mut children_resources = ExecutionResources::default();
 
-    let maybe_entrypoint_steps = if let Some(cairo_execution_info) = &trace.cairo_execution_info {
-        let absolute_source_sierra_path = cairo_execution_info
-            .source_sierra_path
-            .canonicalize_utf8()
-            .with_context(|| {
-                format!(
-                    "Failed to canonicalize path: {}",
-                    cairo_execution_info.source_sierra_path
-                )
-            })?;
-
-        let compiled_artifacts =
-            compiled_artifacts_cache.get_compiled_artifacts_for_path(&absolute_source_sierra_path);
-
-        let mut entrypoint_calls = Vec::new();
-        let mut calldata_lengths = Vec::new();
-
-        for node in &trace.nested_calls {
-            if let CallTraceNode::EntryPointCall(sub_trace) = node {
-                if let Some(len) = sub_trace.entry_point.calldata_len {
-                    calldata_lengths.push(len);
-                }
-                entrypoint_calls.push(sub_trace);
-            }
-        }
-        let mut entrypoint_calls = entrypoint_calls.into_iter().peekable();
-        let in_transaction = !is_transaction_entrypoint(&function_name);
-
-        let function_level_profiling_info = collect_function_level_profiling_info(
-            &compiled_artifacts.sierra_program,
-            &compiled_artifacts.sierra_program_info,
-            &compiled_artifacts.casm_debug_info,
-            &cairo_execution_info.casm_level_info,
-            compiled_artifacts.statements_functions_map.as_ref(),
-            &FunctionLevelConfig::from(profiler_config),
-            versioned_constants,
-            sierra_gas_tracking,
-            calldata_lengths,
-            in_transaction,
-            &mut VecDeque::from(trace.entry_point.events_summary.clone().unwrap_or_default()),
-            profiler_config.cairo_enable_gas,
-        );
+    let maybe_entrypoint_steps = if trace.cairo_execution_info.is_some() {
+        let mut entrypoint_calls = trace
+            .nested_calls
+            .iter()
+            .filter_map(|node| match node {
+                CallTraceNode::EntryPointCall(sub_trace) => Some(sub_trace),
+                CallTraceNode::DeployWithoutConstructor => None,
+            })
+            .peekable();
+
+        let function_level_profiling_info = function_level_profiling_infos
+            .next()
+            .expect("Function level profiling info should be collected for every call with Cairo execution info");
 
         let mut trigger_idx = 0;
 
@@ -230,7 +401,10 @@ fn collect_samples<'a>(
                     samples,
                     &mut triggered_call_stack,
                     sub_trace,
-                    compiled_artifacts_cache,
+                    function_level_profiling_infos,
+                    modified_contracts,
+                    declared_classes,
+                    entrypoint_invocations,
                     profiler_config,
                     versioned_constants,
                     sierra_gas_tracking,
@@ -243,7 +417,10 @@ fn collect_samples<'a>(
                     samples,
                     current_entrypoint_call_stack,
                     sub_trace,
-                    compiled_artifacts_cache,
+                    function_level_profiling_infos,
+                    modified_contracts,
+                    declared_classes,
+                    entrypoint_invocations,
                     profiler_config,
                     versioned_constants,
                     sierra_gas_tracking,
@@ -271,7 +448,7 @@ fn collect_samples<'a>(
         }
 
         let mut function_samples = function_level_profiling_info
-            .functions_samples
+            .samples(versioned_constants, sierra_gas_tracking)?
             .into_iter()
             .map(
                 |Sample {
@@ -285,7 +462,10 @@ fn collect_samples<'a>(
             .collect();
 
         samples.append(&mut function_samples);
-        Some(function_level_profiling_info.header_resources)
+        Some((
+            function_level_profiling_info.header_resources,
+            &function_level_profiling_info.functions_vm_resources,
+        ))
     } else {
         for sub_trace_node in &trace.nested_calls {
             if let CallTraceNode::EntryPointCall(sub_trace) = sub_trace_node {
@@ -293,7 +473,10 @@ fn collect_samples<'a>(
                     samples,
                     current_entrypoint_call_stack,
                     sub_trace,
-                    compiled_artifacts_cache,
+                    function_level_profiling_infos,
+                    modified_contracts,
+                    declared_classes,
+                    entrypoint_invocations,
                     profiler_config,
                     versioned_constants,
                     sierra_gas_tracking,
@@ -307,9 +490,12 @@ fn collect_samples<'a>(
     let mut call_resources = trace.cumulative_resources.clone();
     call_resources.sub_resources(&children_resources);
 
-    if let Some(entrypoint_steps) = maybe_entrypoint_steps {
+    if let Some((entrypoint_steps, functions_vm_resources)) = maybe_entrypoint_steps {
         call_resources.vm_resources.n_steps = entrypoint_steps.steps.0;
         call_resources.gas_consumed = Some(entrypoint_steps.sierra_gas_consumed.0.try_into()?);
+        call_resources
+            .vm_resources
+            .sub_resources(functions_vm_resources);
     }
 
     // Only applies to traces without explicit Cairo execution info
@@ -321,7 +507,7 @@ fn collect_samples<'a>(
             &function_name,
             versioned_constants,
             sierra_gas_tracking,
-        );
+        )?;
     }
 
     let maybe_entrypoint_l2_gas =
@@ -341,11 +527,41 @@ fn collect_samples<'a>(
             None
         };
 
+    if is_tx_entrypoint && sierra_gas_tracking {
+        if profiler_config.deploy_account_costs
+            && let Some(sample) = deploy_account_sample(
+                current_entrypoint_call_stack,
+                &trace.entry_point,
+                versioned_constants,
+            )?
+        {
+            samples.push(sample);
+        }
+        if profiler_config.declare_costs {
+            samples.extend(declared_classes.declare_samples(
+                current_entrypoint_call_stack,
+                trace,
+                versioned_constants,
+            )?);
+        }
+    }
+
+    let maybe_contract_header_l1_data_gas =
+        modified_contracts.contract_header_l1_data_gas(&trace.entry_point, &call_resources);
+
+    // Messages of calls profiled on function level are attributed to `SendMessageToL1` syscalls
+    let entrypoint_l2_l1_message_sizes = if trace.cairo_execution_info.is_some() {
+        &[]
+    } else {
+        trace.used_l1_resources.l2_l1_message_sizes.as_slice()
+    };
+
     samples.push(Sample::from(
         current_entrypoint_call_stack.clone(),
         &call_resources,
-        &trace.used_l1_resources,
+        entrypoint_l2_l1_message_sizes,
         maybe_entrypoint_l2_gas,
+        maybe_contract_header_l1_data_gas,
     ));
 
     current_entrypoint_call_stack.pop();
@@ -353,6 +569,17 @@ fn collect_samples<'a>(
     Ok(&trace.cumulative_resources)
 }
 
+/// Returns the name of the entrypoint frame, suffixed with the number of its invocation.
+fn with_invocation_number(function_name: &FunctionName, invocation: usize) -> FunctionName {
+    // Names of snforge entrypoints end with a newline, unlike the ones of scarb execute
+    let name = &function_name.0;
+    if name.ends_with('\n') {
+        FunctionName(format!("{name}Invocation: {invocation}\n"))
+    } else {
+        FunctionName(format!("{name}\nInvocation: {invocation}"))
+    }
+}
+
 fn try_add_syscalls(
     trace: &CallTraceV1,
     samples: &mut Vec<Sample>,
@@ -360,7 +587,7 @@ fn try_add_syscalls(
     function_name: &FunctionName,
     versioned_constants: &VersionedConstants,
     sierra_gas_tracking: bool,
-) {
+) -> Result<()> {
     match &trace.cumulative_resources.syscall_counter {
         Some(syscall_counter) => {
             collect_syscall_samples(
@@ -369,12 +596,13 @@ fn try_add_syscalls(
                 call_stack,
                 versioned_constants,
                 sierra_gas_tracking,
-            );
+            )?;
         }
         None => {
             emit_missing_syscall_warning(function_name);
         }
     }
+    Ok(())
 }
 
 fn collect_syscall_samples(
@@ -383,7 +611,7 @@ fn collect_syscall_samples(
     base_call_stack: &[FunctionCall],
     versioned_constants: &VersionedConstants,
     sierra_gas_tracking: bool,
-) {
+) -> Result<()> {
     for (selector, usage) in syscall_counter {
         let mut call_stack = base_call_stack.to_vec();
         call_stack.push(FunctionCall::InternalFunctionCall(
@@ -403,9 +631,11 @@ fn collect_syscall_samples(
             Some(usage.linear_factor),
             false,
             &HashMap::default(),
-        );
+            &HashMap::default(),
+        )?;
         samples.push(sample);
     }
+    Ok(())
 }
 
 fn emit_missing_syscall_warning(function_name: &FunctionName) {
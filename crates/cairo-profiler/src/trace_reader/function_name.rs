@@ -2,12 +2,19 @@ use anyhow::{Error, anyhow};
 use cairo_annotations::annotations::profiler::FunctionName;
 use cairo_annotations::trace_data::{ContractAddress, EntryPointSelector};
 use cairo_lang_sierra::program::{Program, StatementIdx};
-use regex::Regex;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fmt;
 use std::sync::LazyLock;
 
 static RE_LOOP_FUNC: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"\[expr\d*\]").expect("Failed to create regex for normalizing loop function names")
 });
+// Loop functions are suffixed with `[exprN]` by older compilers and with a span `[start-end]` by newer ones.
+static RE_LOOP_SUFFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(expr\d*|\d+-\d+)\]$")
+        .expect("Failed to create regex for recognizing loop function names")
+});
 static RE_MONOMORPHIZATION: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"::<.*>")
         .expect("Failed to create regex for normalizing monomorphized generic function names")
@@ -30,6 +37,15 @@ impl ExternalTool {
     }
 }
 
+impl fmt::Display for ExternalTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalTool::Snforge => write!(f, "snforge"),
+            ExternalTool::ScarbExecute => write!(f, "scarb execute"),
+        }
+    }
+}
+
 pub trait FunctionNameExt {
     fn from_entry_point_params(
         contract_name: Option<String>,
@@ -44,6 +60,8 @@ pub trait FunctionNameExt {
         statement_idx: StatementIdx,
         sierra_program: &Program,
         split_generics: bool,
+        show_loops: bool,
+        function_lines: &HashMap<usize, usize>,
     ) -> FunctionName;
 }
 
@@ -94,10 +112,15 @@ impl FunctionNameExt for FunctionName {
     /// Get `FunctionName` from given `sierra_statement_idx` and `sierra_program`
     /// Depending on `split_generics`, the resulting `FunctionName` will retain or remove
     /// the parameterization of generic types (eg <felt252>)
+    /// If `show_loops` is set, loop functions are named after their parent function and the line
+    /// they start at (taken from `function_lines`), e.g. `my_fn::loop@line 42`. Otherwise, they
+    /// are named after their parent function.
     fn from_sierra_statement_idx(
         statement_idx: StatementIdx,
         sierra_program: &Program,
         split_generics: bool,
+        show_loops: bool,
+        function_lines: &HashMap<usize, usize>,
     ) -> Self {
         // The `-1` here can't cause an underflow as the statement id of first function's entrypoint is
         // always 0, so it is always on the left side of the partition, thus the partition index is > 0.
@@ -106,8 +129,19 @@ impl FunctionNameExt for FunctionName {
             .partition_point(|f| f.entry_point.0 <= statement_idx.0)
             - 1;
         let function_name = sierra_program.funcs[function_idx].id.to_string();
-        // Remove suffix in case of loop function e.g. `[expr36]`.
-        let function_name = RE_LOOP_FUNC.replace(&function_name, "");
+        let function_name = if show_loops {
+            // Replace suffix in case of loop function e.g. `[expr36]` with `::loop@line 42`.
+            // The compiler suffix is kept if the line is unknown.
+            RE_LOOP_SUFFIX.replace(&function_name, |captures: &Captures| {
+                match function_lines.get(&function_idx) {
+                    Some(line) => format!("::loop@line {line}"),
+                    None => format!("::loop{}", &captures[0]),
+                }
+            })
+        } else {
+            // Remove suffix in case of loop function e.g. `[expr36]`.
+            RE_LOOP_FUNC.replace(&function_name, "")
+        };
         // Remove parameters from monomorphised Cairo generics e.g. `<felt252>`.
         let function_name = if split_generics {
             function_name
@@ -1,6 +1,11 @@
+use crate::annotation::StatementCosts;
+use crate::libfunc_report::{LibfuncReport, LibfuncUsage};
 use crate::profiler_config::FunctionLevelConfig;
+use crate::sierra_loader::{CompiledArtifacts, PrecomputedProgramData, StorageAddressConstant};
 use crate::trace_reader::function_name::FunctionNameExt;
-use crate::trace_reader::function_trace_builder::cost::{CostEntry, ProfilerInvocationInfo};
+use crate::trace_reader::function_trace_builder::cost::{
+    BuiltinCounts, CostEntry, ProfilerInvocationInfo,
+};
 use crate::trace_reader::function_trace_builder::function_stack_trace::{
     CallStack, VecWithLimitedCapacity,
 };
@@ -10,37 +15,101 @@ use crate::trace_reader::function_trace_builder::stack_trace::{
 };
 use crate::trace_reader::sample::{FunctionCall, InternalFunctionCall, Sample};
 use crate::versioned_constants_reader::VersionedConstants;
+use anyhow::Result;
 use cairo_annotations::annotations::profiler::{FunctionName, ProfilerAnnotationsV1};
-use cairo_annotations::trace_data::{CasmLevelInfo, SummedUpEvent};
+use cairo_annotations::trace_data::{CasmLevelInfo, SummedUpEvent, VmExecutionResources};
 use cairo_annotations::{MappingResult, map_pcs_to_sierra_statement_ids};
-use cairo_lang_sierra::extensions::core::{CoreConcreteLibfunc, CoreLibfunc, CoreType};
+use cairo_lang_sierra::extensions::core::CoreConcreteLibfunc;
 use cairo_lang_sierra::extensions::gas::CostTokenType;
+use cairo_lang_sierra::extensions::mem::MemConcreteLibfunc;
 use cairo_lang_sierra::extensions::starknet::StarknetConcreteLibfunc;
-use cairo_lang_sierra::program::{GenStatement, Program, StatementIdx};
-use cairo_lang_sierra::program_registry::ProgramRegistry;
-use cairo_lang_sierra_gas::compute_precost_info;
+use cairo_lang_sierra::program::{GenStatement, StatementIdx};
 use cairo_lang_sierra_gas::core_libfunc_cost::core_libfunc_cost;
-use cairo_lang_sierra_gas::gas_info::GasInfo;
-use cairo_lang_sierra_to_casm::circuit::CircuitsInfo;
-use cairo_lang_sierra_to_casm::compiler::CairoProgramDebugInfo;
-use cairo_lang_sierra_to_casm::metadata::{Metadata, MetadataComputationConfig, calc_metadata};
-use cairo_lang_sierra_type_size::{ProgramRegistryInfo, TypeSizeMap, get_type_size_map};
 use cairo_lang_utils::ordered_hash_map::OrderedHashMap;
 use cairo_lang_utils::small_ordered_map::SmallOrderedMap;
+use num_bigint::BigUint;
 use std::collections::{HashMap, VecDeque};
 use std::ops::{AddAssign, SubAssign};
 
-mod cost;
+pub mod cost;
 mod function_stack_trace;
 mod inlining;
 pub mod stack_trace;
 
+/// Resources used by functions of a call, collected from its VM trace. Costs depending on versioned
+/// constants are not known until samples are priced with [`FunctionLevelProfilingInfo::samples`],
+/// so the same info can be priced under several versioned constants.
 pub struct FunctionLevelProfilingInfo {
-    pub functions_samples: Vec<Sample>,
+    functions_stack_traces: HashMap<Vec<FunctionCall>, ChargedResources>,
+    syscall_stack_traces: OrderedHashMap<Vec<FunctionCall>, i64>,
+    function_casm_sizes: HashMap<Vec<FunctionCall>, i64>,
+    entrypoint_calldata_lengths: Vec<usize>,
+    calculate_l2_gas: bool,
+    events: HashMap<Vec<FunctionCall>, Vec<SummedUpEvent>>,
+    messages: HashMap<Vec<FunctionCall>, Vec<usize>>,
     pub header_resources: ChargedResources,
+    /// Builtins and memory holes attributed to functions, which are no longer part
+    /// of the entrypoint's own resources.
+    pub functions_vm_resources: VmExecutionResources,
+    /// Whether builtins or memory holes estimated for functions exceeded the ones reported
+    /// in the trace for the entrypoint.
+    pub vm_resources_exceeded_trace: bool,
     /// Call stacks that triggered each `EntryPointCall` in `nested_calls`,
     /// used as the entry context for sample collection.
     pub nested_call_triggers: Vec<Vec<FunctionCall>>,
+    /// Whether any call stack exceeded the maximum depth and lost its deepest functions.
+    pub call_stacks_truncated: bool,
+    /// Empty unless the libfunc report was requested.
+    pub libfunc_report: LibfuncReport,
+    /// Costs of executed sierra statements. Empty unless they were requested.
+    pub statement_costs: HashMap<StatementIdx, StatementCosts>,
+}
+
+impl FunctionLevelProfilingInfo {
+    /// Returns samples of functions and syscalls of the call, priced under `versioned_constants`.
+    pub fn samples(
+        &self,
+        versioned_constants: &VersionedConstants,
+        sierra_gas_tracking: bool,
+    ) -> Result<Vec<Sample>> {
+        trace_to_samples(
+            &self.functions_stack_traces,
+            &self.syscall_stack_traces,
+            &self.function_casm_sizes,
+            versioned_constants,
+            sierra_gas_tracking,
+            &self.entrypoint_calldata_lengths,
+            self.calculate_l2_gas,
+            &self.events,
+            &self.messages,
+        )
+    }
+
+    /// Flags builtins or memory holes estimated for functions which exceed the ones reported
+    /// in the trace for the entrypoint (`entrypoint_vm_resources`, excluding nested calls).
+    /// Traces which report none of them, e.g. ones tracking sierra gas, are not checked.
+    pub fn check_vm_resources_against_trace(
+        &mut self,
+        entrypoint_vm_resources: &VmExecutionResources,
+    ) {
+        let reported = entrypoint_vm_resources.n_memory_holes > 0
+            || !entrypoint_vm_resources.builtin_instance_counter.is_empty();
+        let exceeded = self.functions_vm_resources.n_memory_holes
+            > entrypoint_vm_resources.n_memory_holes
+            || self
+                .functions_vm_resources
+                .builtin_instance_counter
+                .iter()
+                .any(|(builtin, count)| {
+                    *count
+                        > entrypoint_vm_resources
+                            .builtin_instance_counter
+                            .get(builtin)
+                            .copied()
+                            .unwrap_or_default()
+                });
+        self.vm_resources_exceeded_trace = reported && exceeded;
+    }
 }
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
@@ -82,7 +151,16 @@ impl SubAssign for SierraGasConsumed {
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct ChargedResources {
     pub steps: Steps,
+    /// Excludes sierra gas of `charged_builtins`, which depends on versioned constants.
     pub sierra_gas_consumed: SierraGasConsumed,
+    /// Builtins charged in sierra gas. Known only when tracking sierra gas of programs compiled
+    /// with gas enabled.
+    pub charged_builtins: BuiltinCounts,
+    /// Range checks are known for all programs, other builtins only for programs compiled with
+    /// gas enabled.
+    pub builtins: BuiltinCounts,
+    pub memory_holes: usize,
+    pub function_calls: usize,
 }
 
 impl ChargedResources {
@@ -99,35 +177,39 @@ impl AddAssign for ChargedResources {
     fn add_assign(&mut self, rhs: Self) {
         self.steps += rhs.steps;
         self.sierra_gas_consumed += rhs.sierra_gas_consumed;
+        self.charged_builtins += rhs.charged_builtins;
+        self.builtins += rhs.builtins;
+        self.memory_holes += rhs.memory_holes;
+        self.function_calls += rhs.function_calls;
     }
 }
 
-pub struct ProgramInfos {
-    pub precost_info: GasInfo,
-    pub circuits_info: CircuitsInfo,
-    pub type_sizes: TypeSizeMap,
-    pub metadata: Metadata,
-}
-
 /// Collects profiling info of the current run using the trace.
 #[expect(clippy::too_many_lines, clippy::too_many_arguments)]
 pub fn collect_function_level_profiling_info(
-    program: &Program,
-    program_info: &ProgramRegistryInfo,
-    casm_debug_info: &CairoProgramDebugInfo,
+    compiled_artifacts: &CompiledArtifacts,
     casm_level_info: &CasmLevelInfo,
-    statements_functions_map: Option<&ProfilerAnnotationsV1>,
     function_level_config: &FunctionLevelConfig,
-    versioned_constants: &VersionedConstants,
     sierra_gas_tracking: bool,
     entrypoint_calldata_lengths: Vec<usize>,
     in_transaction: bool,
     events: &mut VecDeque<SummedUpEvent>,
+    l2_l1_message_sizes: &mut VecDeque<usize>,
     cairo_enable_gas: bool,
 ) -> FunctionLevelProfilingInfo {
-    let sierra_program_registry = &ProgramRegistry::<CoreType, CoreLibfunc>::new(program).unwrap();
-    let maybe_program_infos = cairo_enable_gas
-        .then(|| compute_program_infos(program, program_info, sierra_program_registry));
+    let program = &compiled_artifacts.sierra_program;
+    let statements_functions_map = compiled_artifacts.statements_functions_map.as_ref();
+    let PrecomputedProgramData {
+        sierra_program_registry,
+        program_infos,
+        libfunc_names,
+        casm_sizes,
+        storage_address_constants,
+        function_lines,
+        storage_variable_names,
+        range_check_usages,
+    } = &compiled_artifacts.precomputed;
+    let maybe_program_infos = program_infos.as_ref();
 
     let mut call_stack = CallStack::new(function_level_config.max_function_stack_trace_depth);
 
@@ -160,31 +242,43 @@ pub fn collect_function_level_profiling_info(
     // Each EmitEvent syscall should have a corresponding event containing keys and data length
     // taken from trace file. Later on the data is used to estimate l2 gas cost of this syscall.
     let mut events_map: HashMap<Vec<FunctionCall>, Vec<SummedUpEvent>> = HashMap::new();
+    // Likewise, each SendMessageToL1 syscall should have a corresponding message payload size,
+    // used to estimate l1 gas cost of this syscall.
+    let mut messages_map: HashMap<Vec<FunctionCall>, Vec<usize>> = HashMap::new();
+    // Selectors of storage variables which addresses were computed most recently, one per frame of
+    // the function call stack. Storage syscalls are assumed to access the innermost of them, which
+    // is forgotten afterwards, as are the ones computed in functions that have returned.
+    let mut storage_variables_in_frames: Vec<Option<&BigUint>> = vec![None];
 
-    let libfunc_map: HashMap<u64, String> = program
-        .libfunc_declarations
-        .iter()
-        .map(|declaration| {
-            (
-                declaration.id.id,
-                declaration.long_id.generic_id.0.clone().to_string(),
-            )
-        })
-        .collect();
-
-    let sierra_statements = map_pcs_to_sierra_statement_ids(casm_debug_info, casm_level_info);
-    // get all sizes to a hashmap, for quicker lookup
-    let mut casm_sizes: HashMap<String, i64> = HashMap::new();
-    for entry in casm_debug_info.sierra_statement_info.clone() {
-        *casm_sizes
-            .entry(entry.instruction_idx.to_string())
-            .or_default() += i64::try_from(entry.end_offset - entry.start_offset)
-            .expect("Failed to convert casm size to i64");
-    }
+    let sierra_statements =
+        map_pcs_to_sierra_statement_ids(&compiled_artifacts.casm_debug_info, casm_level_info);
 
     let mut function_casm_sizes: HashMap<Vec<FunctionCall>, i64> = HashMap::new();
-
-    for statement in sierra_statements {
+    let mut call_stacks_truncated = false;
+    // Usage of libfuncs keyed by their concrete ids and the functions calling them.
+    // Collected only if the libfunc report was requested.
+    let mut libfunc_usages: HashMap<(u64, FunctionName), LibfuncUsage> = HashMap::new();
+    // Collected only if statement costs were requested.
+    let mut statement_costs: HashMap<StatementIdx, StatementCosts> = HashMap::new();
+    // A libfunc invocation spans all consecutive pcs of its statement.
+    let mut previous_statement_idx: Option<StatementIdx> = None;
+    // The first statement of the program and each statement following a function call
+    // start a new invocation of a function.
+    let mut function_entered = true;
+    // Memory holes left in frames of functions, keyed by their call stacks. Locals reserved by
+    // `finalize_locals` are holes until they are stored, which may happen before they are reserved.
+    let mut frames_memory_holes: HashMap<Vec<FunctionCall>, i64> = HashMap::new();
+    // The invocation using range checks which is being executed, with the call stack its range
+    // checks are attributed to and its caller in the libfunc report. They are known once the branch
+    // it takes is.
+    let mut pending_range_checks: Option<(
+        StatementIdx,
+        VecWithLimitedCapacity<FunctionCall>,
+        Option<FunctionName>,
+    )> = None;
+    let vm_trace = &casm_level_info.vm_trace;
+
+    for (step, statement) in sierra_statements.into_iter().enumerate() {
         let sierra_statement_idx = match statement {
             MappingResult::SierraStatementIdx(sierra_statement_idx) => sierra_statement_idx,
             MappingResult::Header => {
@@ -213,32 +307,121 @@ pub fn collect_function_level_profiling_info(
             sierra_statement_idx,
             program,
             function_level_config.split_generics,
+            function_level_config.show_loops,
+            function_lines,
         );
 
         let current_call_stack = build_current_call_stack(
             &call_stack,
             current_function_name,
-            function_level_config.show_inlined_functions,
+            function_level_config,
             sierra_statement_idx,
             statements_functions_map,
         );
+        call_stacks_truncated |= current_call_stack.truncated();
 
         let Some(gen_statement) = program.statements.get(sierra_statement_idx.0) else {
             panic!("Failed fetching statement index {}", sierra_statement_idx.0);
         };
+        let new_invocation = previous_statement_idx != Some(sierra_statement_idx);
+        previous_statement_idx = Some(sierra_statement_idx);
+
+        if new_invocation
+            && let Some((invoking_statement_idx, stack, caller)) = pending_range_checks.take()
+            && let Some(usage) = range_check_usages[&invoking_statement_idx]
+                .iter()
+                .find(|usage| usage.next_statement_idx == sierra_statement_idx)
+        {
+            let range_checks = BuiltinCounts {
+                range_check: usage.range_check,
+                range_check96: usage.range_check96,
+                ..BuiltinCounts::default()
+            };
+            functions_stack_traces
+                .entry(stack.into())
+                .or_default()
+                .builtins += range_checks;
+            if function_level_config.statement_costs {
+                statement_costs
+                    .entry(invoking_statement_idx)
+                    .or_default()
+                    .builtins += range_checks;
+            }
+            if let Some(caller) = caller
+                && let GenStatement::Invocation(invocation) =
+                    &program.statements[invoking_statement_idx.0]
+            {
+                *libfunc_usages
+                    .entry((invocation.libfunc_id.id, caller))
+                    .or_default() += LibfuncUsage {
+                    builtins: range_checks,
+                    ..LibfuncUsage::default()
+                };
+            }
+        }
 
-        let profiler_info_provider =
-            maybe_program_infos
-                .as_ref()
-                .map(|infos| ProfilerInvocationInfo {
-                    type_sizes: &infos.type_sizes,
-                    circuits_info: &infos.circuits_info,
-                    metadata: &infos.metadata,
-                    idx: sierra_statement_idx,
-                });
+        if function_level_config.statement_costs {
+            *statement_costs.entry(sierra_statement_idx).or_default() += StatementCosts {
+                executions: usize::from(new_invocation),
+                steps: 1,
+                ..StatementCosts::default()
+            };
+        }
+
+        if function_entered {
+            functions_stack_traces
+                .entry(without_inlined_calls(&current_call_stack).into())
+                .or_default()
+                .function_calls += 1;
+            function_entered = false;
+        }
+
+        let profiler_info_provider = maybe_program_infos.map(|infos| ProfilerInvocationInfo {
+            type_sizes: &infos.type_sizes,
+            circuits_info: &infos.circuits_info,
+            metadata: &infos.metadata,
+            idx: sierra_statement_idx,
+        });
 
         match gen_statement {
             GenStatement::Invocation(invocation) => {
+                if function_level_config.libfunc_report {
+                    let mut usage = LibfuncUsage {
+                        calls: usize::from(new_invocation),
+                        ..LibfuncUsage::default()
+                    };
+                    if sierra_gas_tracking {
+                        usage.sierra_gas = 100;
+                    } else {
+                        usage.steps = 1;
+                    }
+                    record_libfunc_usage(
+                        &mut libfunc_usages,
+                        invocation.libfunc_id.id,
+                        &current_call_stack,
+                        usage,
+                    );
+                }
+
+                if function_level_config.show_storage_variables
+                    && let Some(constants) = storage_address_constants.get(&sierra_statement_idx)
+                {
+                    let storage_variable =
+                        constants.iter().rev().find_map(|constant| match constant {
+                            StorageAddressConstant::BaseAddress(selector) => Some(selector),
+                            StorageAddressConstant::Felt252(value) => (function_level_config
+                                .storage_variable_names
+                                .contains_key(value)
+                                || storage_variable_names.contains_key(value))
+                            .then_some(value),
+                        });
+                    if storage_variable.is_some()
+                        && let Some(frame) = storage_variables_in_frames.last_mut()
+                    {
+                        *frame = storage_variable;
+                    }
+                }
+
                 let libfunc = sierra_program_registry.get_libfunc(&invocation.libfunc_id);
 
                 match libfunc {
@@ -254,6 +437,8 @@ pub fn collect_function_level_profiling_info(
                             .get(&sierra_statement_idx.to_string())
                             .unwrap_or(&0);
                         call_stack.enter_function_call(current_call_stack);
+                        function_entered = true;
+                        storage_variables_in_frames.push(None);
                     }
                     Ok(CoreConcreteLibfunc::Starknet(libfunc)) => {
                         increment_resource(
@@ -288,8 +473,30 @@ pub fn collect_function_level_profiling_info(
                             }
                         };
                         if in_syscall_idx.as_ref() != Some(&sierra_statement_idx) {
+                            let storage_variable = (function_level_config.show_storage_variables
+                                && matches!(
+                                    syscall,
+                                    StarknetConcreteLibfunc::StorageRead(_)
+                                        | StarknetConcreteLibfunc::StorageWrite(_)
+                                ))
+                            .then(|| {
+                                let frame = storage_variables_in_frames
+                                    .iter()
+                                    .rposition(Option::is_some);
+                                let selector = frame.and_then(|frame| {
+                                    let selector = storage_variables_in_frames[frame];
+                                    storage_variables_in_frames[frame..].fill(None);
+                                    selector
+                                });
+                                storage_variable_name(
+                                    selector,
+                                    &function_level_config.storage_variable_names,
+                                    storage_variable_names,
+                                )
+                            });
                             register_syscall(
                                 syscall,
+                                storage_variable,
                                 sierra_statement_idx,
                                 &mut in_syscall_idx,
                                 &current_call_stack,
@@ -297,6 +504,8 @@ pub fn collect_function_level_profiling_info(
                                 &mut syscall_stack_traces,
                                 &mut events_map,
                                 events,
+                                &mut messages_map,
+                                l2_l1_message_sizes,
                             );
                         }
                     }
@@ -305,7 +514,7 @@ pub fn collect_function_level_profiling_info(
                         if in_syscall_idx.is_none() {
                             let libfunc_name = if function_level_config.show_libfuncs {
                                 Some(
-                                    libfunc_map
+                                    libfunc_names
                                         .get(&invocation.libfunc_id.id)
                                         .expect("Failed to find libfunc in map")
                                         .as_str(),
@@ -323,26 +532,89 @@ pub fn collect_function_level_profiling_info(
                                 sierra_gas_tracking,
                             );
 
-                            // We can only calculate a libfunc additional cost when tracking sierra
-                            // and when gas was enabled during cairo compilation, otherwise the info
-                            // cannot be obtained at all
-                            if sierra_gas_tracking && cairo_enable_gas {
-                                let precost_info =
-                                    &maybe_program_infos.as_ref().unwrap().precost_info;
+                            if new_invocation
+                                && range_check_usages.contains_key(&sierra_statement_idx)
+                            {
+                                let caller = function_level_config
+                                    .libfunc_report
+                                    .then(|| current_call_stack.iter().last())
+                                    .flatten()
+                                    .map(|call| call.function_name().clone());
+                                pending_range_checks =
+                                    Some((sierra_statement_idx, effective_stack.clone(), caller));
+                            }
+
+                            // Memory holes are left by `branch_align` and `finalize_locals`, which
+                            // advance `ap` over cells that are never written, except for locals
+                            // filled afterwards by `store_local`, one cell per step
+                            let memory_holes = match libfunc {
+                                Ok(
+                                    CoreConcreteLibfunc::BranchAlign(_)
+                                    | CoreConcreteLibfunc::Mem(MemConcreteLibfunc::FinalizeLocals(_)),
+                                ) => vm_trace.get(step + 1).map_or(0, |next_step| {
+                                    i64::try_from(next_step.ap - vm_trace[step].ap).unwrap()
+                                }),
+                                Ok(CoreConcreteLibfunc::Mem(MemConcreteLibfunc::StoreLocal(_))) => {
+                                    -1
+                                }
+                                _ => 0,
+                            };
+                            if memory_holes != 0 {
+                                // Locals are stored in the function they were allocated in,
+                                // so their holes are never split between inlined functions
+                                *frames_memory_holes
+                                    .entry(without_inlined_calls(&current_call_stack).into())
+                                    .or_default() += memory_holes;
+                            }
+
+                            // Builtins other than range checks can only be counted when gas was
+                            // enabled during cairo compilation, as they are known from cost tokens
+                            let builtins = if cairo_enable_gas {
+                                let precost_info = &maybe_program_infos.unwrap().precost_info;
                                 let cost_vector = core_libfunc_cost(
                                     precost_info,
                                     sierra_statement_idx,
-                                    libfunc.expect("fatal: expected libfunc, but did not found in sierra registry"),
+                                    libfunc.expect(
+                                        "fatal: expected libfunc, but did not found in sierra registry",
+                                    ),
                                     &profiler_info_provider.expect("fatal: enable-gas was set in cairo, but program infos is unavailable!"),
                                 );
+                                let builtins =
+                                    count_builtins(&cost_vector, &mut libfunc_appearance_tracker);
+                                functions_stack_traces
+                                    .entry(effective_stack.clone().into())
+                                    .or_default()
+                                    .builtins += builtins;
+                                if function_level_config.statement_costs {
+                                    statement_costs
+                                        .entry(sierra_statement_idx)
+                                        .or_default()
+                                        .builtins += builtins;
+                                }
+                                if function_level_config.libfunc_report {
+                                    record_libfunc_usage(
+                                        &mut libfunc_usages,
+                                        invocation.libfunc_id.id,
+                                        &current_call_stack,
+                                        LibfuncUsage {
+                                            builtins,
+                                            ..LibfuncUsage::default()
+                                        },
+                                    );
+                                }
+                                builtins
+                            } else {
+                                BuiltinCounts::default()
+                            };
 
-                                add_builtin_sierra_costs(
-                                    &cost_vector,
-                                    &mut libfunc_appearance_tracker,
-                                    versioned_constants,
-                                    &mut functions_stack_traces,
-                                    &effective_stack,
-                                );
+                            // We can only calculate a libfunc additional cost when tracking sierra
+                            // and when gas was enabled during cairo compilation, otherwise the info
+                            // cannot be obtained at all
+                            if sierra_gas_tracking && cairo_enable_gas {
+                                functions_stack_traces
+                                    .entry(effective_stack.clone().into())
+                                    .or_default()
+                                    .charged_builtins += builtins;
                             }
                             *function_casm_sizes
                                 .entry(effective_stack.clone().into())
@@ -372,47 +644,86 @@ pub fn collect_function_level_profiling_info(
                 if call_stack.exit_function_call().is_none() {
                     end_of_program_reached = true;
                 }
+                if storage_variables_in_frames.len() > 1 {
+                    storage_variables_in_frames.pop();
+                }
             }
         }
     }
 
-    let functions_samples = trace_to_samples(
+    for (call_stack, memory_holes) in frames_memory_holes {
+        functions_stack_traces
+            .entry(call_stack)
+            .or_default()
+            .memory_holes += usize::try_from(memory_holes.max(0)).unwrap();
+    }
+
+    let mut functions_vm_resources = VmExecutionResources::default();
+    for resources in functions_stack_traces.values() {
+        functions_vm_resources.n_memory_holes += resources.memory_holes;
+        for (builtin, count) in resources.builtins.iter() {
+            *functions_vm_resources
+                .builtin_instance_counter
+                .entry(builtin.to_string())
+                .or_default() += count;
+        }
+    }
+
+    let mut libfunc_report = LibfuncReport::default();
+    for ((libfunc_id, caller), usage) in libfunc_usages {
+        let libfunc_name = libfunc_names
+            .get(&libfunc_id)
+            .expect("Failed to find libfunc in map");
+        libfunc_report.add(libfunc_name, caller, usage);
+    }
+
+    FunctionLevelProfilingInfo {
         functions_stack_traces,
         syscall_stack_traces,
-        &function_casm_sizes,
-        versioned_constants,
-        sierra_gas_tracking,
+        function_casm_sizes,
         entrypoint_calldata_lengths,
-        in_transaction && cairo_enable_gas,
-        &events_map,
-    );
-
-    FunctionLevelProfilingInfo {
-        functions_samples,
+        calculate_l2_gas: in_transaction && cairo_enable_gas,
+        events: events_map,
+        messages: messages_map,
         header_resources,
+        functions_vm_resources,
+        vm_resources_exceeded_trace: false,
         nested_call_triggers,
+        call_stacks_truncated,
+        libfunc_report,
+        statement_costs,
     }
 }
 
 fn build_current_call_stack(
     call_stack: &CallStack,
     current_function_name: FunctionName,
-    show_inlined_functions: bool,
+    function_level_config: &FunctionLevelConfig,
     sierra_statement_idx: StatementIdx,
     statements_functions_map: Option<&ProfilerAnnotationsV1>,
 ) -> VecWithLimitedCapacity<FunctionCall> {
     let mut current_call_stack = call_stack.current_call_stack().clone();
-
-    if current_call_stack.is_empty()
-        || *current_call_stack[current_call_stack.len() - 1].function_name()
-            != current_function_name
+    let current_function_call =
+        FunctionCall::InternalFunctionCall(InternalFunctionCall::NonInlined(current_function_name));
+
+    // This runs on every step, so the caller stack is cloned only if recursion is collapsed
+    let recursive_caller_stack = function_level_config
+        .collapse_recursion
+        .then(|| without_inlined_calls(&current_call_stack))
+        .filter(|caller_stack| caller_stack.iter().last() == Some(&current_function_call));
+
+    if let Some(caller_stack) = recursive_caller_stack {
+        // Attribute the recursive call to the calling frame of the same function,
+        // dropping the inlined calls it was made from
+        current_call_stack = caller_stack;
+    } else if current_call_stack.is_empty()
+        || current_call_stack[current_call_stack.len() - 1].function_name()
+            != current_function_call.function_name()
     {
-        current_call_stack.push(FunctionCall::InternalFunctionCall(
-            InternalFunctionCall::NonInlined(current_function_name),
-        ));
+        current_call_stack.push(current_function_call);
     }
 
-    if show_inlined_functions {
+    if function_level_config.show_inlined_functions {
         build_original_call_stack_with_inlined_calls(
             sierra_statement_idx,
             statements_functions_map,
@@ -423,25 +734,6 @@ fn build_current_call_stack(
     }
 }
 
-fn sum_builtins_cost(branch_cost: &CostEntry, versioned_constants: &VersionedConstants) -> u64 {
-    let mut post_cost: u64 = u64::default();
-
-    for (token, cost) in branch_cost.iter() {
-        let cost = u64::try_from(cost.max(0)).expect("Cost must be non-negative after clamping");
-        let builtin_cost = match token {
-            CostTokenType::Pedersen => versioned_constants.os_constants.builtin_gas_costs.pedersen,
-            CostTokenType::Poseidon => versioned_constants.os_constants.builtin_gas_costs.poseidon,
-            CostTokenType::Bitwise => versioned_constants.os_constants.builtin_gas_costs.bitwise,
-            CostTokenType::EcOp => versioned_constants.os_constants.builtin_gas_costs.ecop,
-            CostTokenType::AddMod => versioned_constants.os_constants.builtin_gas_costs.add_mod,
-            CostTokenType::MulMod => versioned_constants.os_constants.builtin_gas_costs.mul_mod,
-            _ => continue,
-        };
-        post_cost += builtin_cost * cost;
-    }
-    post_cost
-}
-
 fn increment_resource(
     functions_stack_traces: &mut HashMap<Vec<FunctionCall>, ChargedResources>,
     call_stack: &VecWithLimitedCapacity<FunctionCall>,
@@ -453,6 +745,41 @@ fn increment_resource(
         .increment(sierra_gas_tracking);
 }
 
+/// Attributes `usage` of the libfunc to the innermost function of `current_call_stack`.
+fn record_libfunc_usage(
+    libfunc_usages: &mut HashMap<(u64, FunctionName), LibfuncUsage>,
+    libfunc_id: u64,
+    current_call_stack: &VecWithLimitedCapacity<FunctionCall>,
+    usage: LibfuncUsage,
+) {
+    if let Some(caller) = current_call_stack.iter().last() {
+        *libfunc_usages
+            .entry((libfunc_id, caller.function_name().clone()))
+            .or_default() += usage;
+    }
+}
+
+/// Returns the stack without the trailing inlined calls, ending with the innermost non-inlined
+/// function.
+fn without_inlined_calls(
+    current_call_stack: &VecWithLimitedCapacity<FunctionCall>,
+) -> VecWithLimitedCapacity<FunctionCall> {
+    let mut stack = current_call_stack.clone();
+    let non_inlined_len = current_call_stack
+        .iter()
+        .enumerate()
+        .filter(|(_, call)| {
+            !matches!(
+                call,
+                FunctionCall::InternalFunctionCall(InternalFunctionCall::Inlined(_))
+            )
+        })
+        .last()
+        .map_or(0, |(position, _)| position + 1);
+    stack.truncate(non_inlined_len);
+    stack
+}
+
 fn effective_call_stack(
     current_stack: &VecWithLimitedCapacity<FunctionCall>,
     libfunc_name: Option<&str>,
@@ -460,7 +787,7 @@ fn effective_call_stack(
     match libfunc_name {
         Some(name) => {
             let mut stack = current_stack.clone();
-            stack.push(FunctionCall::InternalFunctionCall(
+            stack.push_leaf(FunctionCall::InternalFunctionCall(
                 InternalFunctionCall::Libfunc(FunctionName(name.to_owned())),
             ));
             stack
@@ -472,6 +799,7 @@ fn effective_call_stack(
 #[expect(clippy::too_many_arguments)]
 fn register_syscall(
     syscall: &StarknetConcreteLibfunc,
+    storage_variable: Option<FunctionName>,
     sierra_statement_idx: StatementIdx,
     in_syscall_idx: &mut Option<StatementIdx>,
     current_call_stack: &VecWithLimitedCapacity<FunctionCall>,
@@ -479,15 +807,23 @@ fn register_syscall(
     syscall_stack_traces: &mut OrderedHashMap<Vec<FunctionCall>, i64>,
     events_map: &mut HashMap<Vec<FunctionCall>, Vec<SummedUpEvent>>,
     events: &mut VecDeque<SummedUpEvent>,
+    messages_map: &mut HashMap<Vec<FunctionCall>, Vec<usize>>,
+    l2_l1_message_sizes: &mut VecDeque<usize>,
 ) {
     *in_syscall_idx = Some(sierra_statement_idx);
 
     let mut current_call_stack_with_syscall = current_call_stack.clone();
 
-    current_call_stack_with_syscall.push(FunctionCall::InternalFunctionCall(
+    current_call_stack_with_syscall.push_leaf(FunctionCall::InternalFunctionCall(
         InternalFunctionCall::Syscall(FunctionName(map_syscall_to_selector(syscall).to_string())),
     ));
 
+    if let Some(storage_variable) = storage_variable {
+        current_call_stack_with_syscall.push_leaf(FunctionCall::InternalFunctionCall(
+            InternalFunctionCall::StorageVariable(storage_variable),
+        ));
+    }
+
     match syscall {
         StarknetConcreteLibfunc::Deploy(_)
         | StarknetConcreteLibfunc::CallContract(_)
@@ -501,6 +837,14 @@ fn register_syscall(
                 .push(events.front().unwrap().clone());
             events.pop_front();
         }
+        StarknetConcreteLibfunc::SendMessageToL1(_) => {
+            if let Some(message_size) = l2_l1_message_sizes.pop_front() {
+                messages_map
+                    .entry(current_call_stack_with_syscall.clone().into())
+                    .or_default()
+                    .push(message_size);
+            }
+        }
         _ => {}
     }
 
@@ -509,13 +853,34 @@ fn register_syscall(
         .or_insert(0) += 1;
 }
 
-fn add_builtin_sierra_costs(
+/// Names the storage variable with the given selector, preferring names given by the user
+/// over the ones guessed from identifiers in the compiled artifacts of the program. Guessed names
+/// end with `?`, so they are not taken for the actual names of the variables.
+fn storage_variable_name(
+    selector: Option<&BigUint>,
+    storage_variable_names: &HashMap<BigUint, String>,
+    program_storage_variable_names: &HashMap<BigUint, String>,
+) -> FunctionName {
+    FunctionName(match selector {
+        Some(selector) => storage_variable_names
+            .get(selector)
+            .cloned()
+            .or_else(|| {
+                program_storage_variable_names
+                    .get(selector)
+                    .map(|name| format!("{name}?"))
+            })
+            .unwrap_or_else(|| format!("{selector:#x}")),
+        None => "<unknown storage variable>".to_string(),
+    })
+}
+
+/// Returns builtins used by the libfunc, known from its cost tokens.
+fn count_builtins(
     cost_vector: &Vec<SmallOrderedMap<CostTokenType, i64>>,
     libfunc_appearance_tracker: &mut i64,
-    versioned_constants: &VersionedConstants,
-    functions_stack_traces: &mut HashMap<Vec<FunctionCall>, ChargedResources>,
-    libfunc_call_stack: &VecWithLimitedCapacity<FunctionCall>,
-) {
+) -> BuiltinCounts {
+    let mut builtins = BuiltinCounts::default();
     for branch_cost_map in cost_vector {
         let branch_cost = CostEntry::from_map(branch_cost_map);
 
@@ -523,18 +888,7 @@ fn add_builtin_sierra_costs(
         // we use sierra gas estimated by `core_libfunc_cost` function to do this
         if branch_cost.konst == 100 || branch_cost.konst <= *libfunc_appearance_tracker {
             // if a given invocation "costs" some builtins, sum them
-            let post_cost = sum_builtins_cost(&branch_cost, versioned_constants);
-
-            // add builtin cost (resources) to current function in stack
-            *functions_stack_traces
-                .entry(libfunc_call_stack.clone().into())
-                .or_default() += ChargedResources {
-                steps: Steps::default(),
-                sierra_gas_consumed: SierraGasConsumed(
-                    usize::try_from(post_cost)
-                        .expect("Overflow while converting post_cost to usize"),
-                ),
-            };
+            builtins += branch_cost.builtin_counts();
 
             *libfunc_appearance_tracker = 100;
         // if an invocation takes more than 1 step (100 sierra gas), we skip getting its cost for subsequent
@@ -543,29 +897,5 @@ fn add_builtin_sierra_costs(
             *libfunc_appearance_tracker += 100;
         }
     }
-}
-
-fn compute_program_infos(
-    program: &Program,
-    program_info: &ProgramRegistryInfo,
-    sierra_program_registry: &ProgramRegistry<CoreType, CoreLibfunc>,
-) -> ProgramInfos {
-    let precost_info =
-        compute_precost_info(program, program_info).expect("Failed to compute pre-cost info");
-    let circuits_info = CircuitsInfo::new(
-        sierra_program_registry,
-        program.type_declarations.iter().map(|td| &td.id),
-    )
-    .expect("Failed to compute circuits info");
-    let type_sizes =
-        get_type_size_map(program, sierra_program_registry).expect("Failed to get type-size map");
-    let metadata = calc_metadata(program, program_info, MetadataComputationConfig::default())
-        .expect("Failed to compute metadata");
-
-    ProgramInfos {
-        precost_info,
-        circuits_info,
-        type_sizes,
-        metadata,
-    }
+    builtins
 }
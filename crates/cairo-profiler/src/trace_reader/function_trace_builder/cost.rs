@@ -1,3 +1,4 @@
+use crate::versioned_constants_reader::BuiltinGasCosts;
 use cairo_lang_sierra::extensions::circuit::CircuitInfo;
 use cairo_lang_sierra::extensions::gas::CostTokenType;
 use cairo_lang_sierra::ids::ConcreteTypeId;
@@ -8,7 +9,8 @@ use cairo_lang_sierra_to_casm::circuit::CircuitsInfo;
 use cairo_lang_sierra_to_casm::metadata::Metadata;
 use cairo_lang_sierra_type_size::TypeSizeMap;
 use cairo_lang_utils::{casts::IntoOrPanic, small_ordered_map::SmallOrderedMap};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use std::ops::AddAssign;
 
 pub struct ProfilerInvocationInfo<'a> {
     pub type_sizes: &'a TypeSizeMap,
@@ -83,16 +85,90 @@ impl CostEntry {
         }
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (CostTokenType, i64)> + '_ {
+    pub fn builtin_counts(&self) -> BuiltinCounts {
+        let count = |cost: i64| usize::try_from(cost.max(0)).unwrap();
+        BuiltinCounts {
+            pedersen: count(self.pedersen),
+            poseidon: count(self.poseidon),
+            bitwise: count(self.bitwise),
+            ec_op: count(self.ec_op),
+            add_mod: count(self.add_mod),
+            mul_mod: count(self.mul_mod),
+            ..BuiltinCounts::default()
+        }
+    }
+}
+
+/// Instances of builtins used by libfuncs. Range checks are known from the compiled program,
+/// as their cost is merged into the constant cost token, and the others from their cost tokens.
+/// Serialized as a map of the builtins used, keyed by their names.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BuiltinCounts {
+    pub range_check: usize,
+    pub range_check96: usize,
+    pub pedersen: usize,
+    pub poseidon: usize,
+    pub bitwise: usize,
+    pub ec_op: usize,
+    pub add_mod: usize,
+    pub mul_mod: usize,
+}
+
+impl BuiltinCounts {
+    /// Iterates over the counts keyed by builtin names, as they appear in execution resources.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> {
         [
-            (CostTokenType::Const, self.konst),
-            (CostTokenType::Bitwise, self.bitwise),
-            (CostTokenType::Pedersen, self.pedersen),
-            (CostTokenType::Poseidon, self.poseidon),
-            (CostTokenType::EcOp, self.ec_op),
-            (CostTokenType::AddMod, self.add_mod),
-            (CostTokenType::MulMod, self.mul_mod),
+            ("range_check_builtin", self.range_check),
+            ("range_check96_builtin", self.range_check96),
+            ("pedersen_builtin", self.pedersen),
+            ("poseidon_builtin", self.poseidon),
+            ("bitwise_builtin", self.bitwise),
+            ("ec_op_builtin", self.ec_op),
+            ("add_mod_builtin", self.add_mod),
+            ("mul_mod_builtin", self.mul_mod),
         ]
         .into_iter()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().all(|(_, count)| count == 0)
+    }
+
+    /// Sierra gas charged for the builtins according to `builtin_gas_costs`. Range checks are left
+    /// out, as they are charged as part of the constant cost of libfuncs.
+    pub fn sierra_gas(&self, builtin_gas_costs: &BuiltinGasCosts) -> usize {
+        [
+            (self.pedersen, builtin_gas_costs.pedersen),
+            (self.poseidon, builtin_gas_costs.poseidon),
+            (self.bitwise, builtin_gas_costs.bitwise),
+            (self.ec_op, builtin_gas_costs.ecop),
+            (self.add_mod, builtin_gas_costs.add_mod),
+            (self.mul_mod, builtin_gas_costs.mul_mod),
+        ]
+        .into_iter()
+        .map(|(count, cost)| {
+            usize::try_from(cost).expect("Overflow while converting builtin_gas_cost to usize")
+                * count
+        })
+        .sum()
+    }
+}
+
+impl Serialize for BuiltinCounts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter().filter(|(_, count)| *count > 0))
+    }
+}
+
+impl AddAssign for BuiltinCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.range_check += rhs.range_check;
+        self.range_check96 += rhs.range_check96;
+        self.pedersen += rhs.pedersen;
+        self.poseidon += rhs.poseidon;
+        self.bitwise += rhs.bitwise;
+        self.ec_op += rhs.ec_op;
+        self.add_mod += rhs.add_mod;
+        self.mul_mod += rhs.mul_mod;
+    }
 }
@@ -1,32 +1,50 @@
 use crate::trace_reader::sample::FunctionCall;
+use std::mem;
 use std::ops::Index;
 
 /// The function call stack of the current function, excluding the current function call.
 pub(super) struct CallStack {
     stack: VecWithLimitedCapacity<FunctionCall>,
-    /// The last element of this vector is always a number of elements of the stack before the last
-    /// function call.
-    previous_stack_lengths: Vec<usize>,
+    /// The last element of this vector is always the stack before the last function call.
+    previous_stacks: Vec<PreviousStack>,
+}
+
+/// The stack before a function call. It is kept whole only if the call replaced it, which happens
+/// when recursion was collapsed - otherwise the new stack extends it, and only its length is kept.
+enum PreviousStack {
+    Length(usize),
+    Replaced(VecWithLimitedCapacity<FunctionCall>),
 }
 
 impl CallStack {
     pub fn new(max_function_stack_trace_depth: usize) -> Self {
         Self {
             stack: VecWithLimitedCapacity::new(max_function_stack_trace_depth),
-            previous_stack_lengths: vec![],
+            previous_stacks: vec![],
         }
     }
 
-    // The current call stack is always a prefix of the new call stack.
+    // The new call stack extends the current one, unless recursion was collapsed in it.
     pub fn enter_function_call(&mut self, new_call_stack: VecWithLimitedCapacity<FunctionCall>) {
-        self.previous_stack_lengths.push(self.stack.len());
-
-        self.stack = new_call_stack;
+        let extends_current_stack = new_call_stack.len() >= self.stack.len()
+            && self
+                .stack
+                .iter()
+                .zip(new_call_stack.iter())
+                .all(|(a, b)| a == b);
+        let previous_stack = if extends_current_stack {
+            PreviousStack::Length(mem::replace(&mut self.stack, new_call_stack).len())
+        } else {
+            PreviousStack::Replaced(mem::replace(&mut self.stack, new_call_stack))
+        };
+        self.previous_stacks.push(previous_stack);
     }
 
     pub fn exit_function_call(&mut self) -> Option<()> {
-        let previous_stack_len = self.previous_stack_lengths.pop()?;
-        self.stack.truncate(previous_stack_len);
+        match self.previous_stacks.pop()? {
+            PreviousStack::Length(previous_stack_len) => self.stack.truncate(previous_stack_len),
+            PreviousStack::Replaced(previous_stack) => self.stack = previous_stack,
+        }
         Some(())
     }
 
@@ -39,6 +57,8 @@ impl CallStack {
 pub struct VecWithLimitedCapacity<T> {
     vector: Vec<T>,
     max_capacity: usize,
+    /// Whether any element was dropped, because the capacity was exceeded.
+    truncated: bool,
 }
 
 impl<T> VecWithLimitedCapacity<T> {
@@ -46,15 +66,28 @@ impl<T> VecWithLimitedCapacity<T> {
         Self {
             vector: vec![],
             max_capacity,
+            truncated: false,
         }
     }
 
     pub fn push(&mut self, el: T) {
         if self.vector.len() < self.max_capacity {
             self.vector.push(el);
+        } else {
+            self.truncated = true;
         }
     }
 
+    /// Pushes the element regardless of the capacity. Used for leaves of the function tree
+    /// (e.g. syscalls), which must not be lost, as their samples are identified by them.
+    pub fn push_leaf(&mut self, el: T) {
+        self.vector.push(el);
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
     pub fn truncate(&mut self, len: usize) {
         self.vector.truncate(len);
     }
@@ -66,6 +99,10 @@ impl<T> VecWithLimitedCapacity<T> {
     pub fn is_empty(&self) -> bool {
         self.vector.is_empty()
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.vector.iter()
+    }
 }
 
 impl<T> From<VecWithLimitedCapacity<T>> for Vec<T> {
@@ -1,7 +1,12 @@
 use crate::trace_reader::function_trace_builder::ChargedResources;
-use crate::trace_reader::sample::{FunctionCall, MeasurementUnit, MeasurementValue, Sample};
+use crate::trace_reader::l2_l1_messages::add_l2_l1_messages_measurements;
+use crate::trace_reader::sample::{
+    FunctionCall, InternalFunctionCall, MeasurementUnit, MeasurementValue, Sample,
+};
+use crate::trace_reader::state_diff::syscall_l1_data_gas;
 use crate::versioned_constants_reader::SyscallVariant::{Scaled, Unscaled};
 use crate::versioned_constants_reader::{BuiltinGasCosts, VersionedConstants};
+use anyhow::Result;
 use cairo_annotations::trace_data::{
     DeprecatedSyscallSelector, SummedUpEvent, VmExecutionResources,
 };
@@ -14,47 +19,58 @@ use std::collections::HashMap;
 
 #[expect(clippy::too_many_arguments)]
 pub fn trace_to_samples(
-    functions_stack_traces: HashMap<Vec<FunctionCall>, ChargedResources>,
-    syscall_stack_traces: OrderedHashMap<Vec<FunctionCall>, i64>,
+    functions_stack_traces: &HashMap<Vec<FunctionCall>, ChargedResources>,
+    syscall_stack_traces: &OrderedHashMap<Vec<FunctionCall>, i64>,
     function_casm_sizes: &HashMap<Vec<FunctionCall>, i64>,
     versioned_constants: &VersionedConstants,
     sierra_gas_tracking: bool,
-    entrypoint_calldata_lengths: Vec<usize>,
+    entrypoint_calldata_lengths: &[usize],
     calculate_l2_gas: bool,
     events: &HashMap<Vec<FunctionCall>, Vec<SummedUpEvent>>,
-) -> Vec<Sample> {
+    messages: &HashMap<Vec<FunctionCall>, Vec<usize>>,
+) -> Result<Vec<Sample>> {
     let function_samples: Vec<Sample> = functions_stack_traces
-        .into_iter()
+        .iter()
         .map(|(call_stack, cr)| {
-            map_function_trace_to_sample(call_stack, cr, function_casm_sizes, calculate_l2_gas)
+            map_function_trace_to_sample(
+                call_stack.clone(),
+                cr,
+                cr.charged_builtins
+                    .sierra_gas(&versioned_constants.os_constants.builtin_gas_costs),
+                function_casm_sizes,
+                calculate_l2_gas,
+            )
         })
         .collect();
 
     let mut syscall_samples: Vec<Sample> = Vec::new();
-    let mut calldata_lengths_iter = entrypoint_calldata_lengths.into_iter();
+    let mut calldata_lengths_iter = entrypoint_calldata_lengths.iter().copied();
 
-    for (call_stack, invocations) in syscall_stack_traces {
+    for (call_stack, &invocations) in syscall_stack_traces.iter() {
         let sample = map_syscall_trace_to_sample(
-            call_stack,
+            call_stack.clone(),
             invocations,
             versioned_constants,
             sierra_gas_tracking,
             calldata_lengths_iter.next(),
             calculate_l2_gas,
             events,
-        );
+            messages,
+        )?;
         syscall_samples.push(sample);
     }
 
-    [function_samples, syscall_samples].concat()
+    Ok([function_samples, syscall_samples].concat())
 }
 
 fn map_function_trace_to_sample(
     call_stack: Vec<FunctionCall>,
-    cr: ChargedResources,
+    cr: &ChargedResources,
+    builtins_sierra_gas: usize,
     casm_sizes: &HashMap<Vec<FunctionCall>, i64>,
     calculate_l2_gas: bool,
 ) -> Sample {
+    let sierra_gas = i64::try_from(cr.sierra_gas_consumed.0 + builtins_sierra_gas).unwrap();
     let mut measurements = vec![
         (
             MeasurementUnit::from("steps".to_string()),
@@ -62,7 +78,7 @@ fn map_function_trace_to_sample(
         ),
         (
             MeasurementUnit::from("sierra_gas".to_string()),
-            MeasurementValue(i64::try_from(cr.sierra_gas_consumed.0).unwrap()),
+            MeasurementValue(sierra_gas),
         ),
         (
             MeasurementUnit::from("casm_size".to_string()),
@@ -73,9 +89,24 @@ fn map_function_trace_to_sample(
     if calculate_l2_gas {
         measurements.push((
             MeasurementUnit::from("l2_gas".to_string()),
-            MeasurementValue(i64::try_from(cr.sierra_gas_consumed.0).unwrap()),
+            MeasurementValue(sierra_gas),
+        ));
+    }
+
+    for (builtin, count) in cr.builtins.iter() {
+        measurements.push((
+            MeasurementUnit::from(builtin.to_string()),
+            MeasurementValue(i64::try_from(count).unwrap()),
         ));
     }
+    measurements.push((
+        MeasurementUnit::from("memory_holes".to_string()),
+        MeasurementValue(i64::try_from(cr.memory_holes).unwrap()),
+    ));
+    measurements.push((
+        MeasurementUnit::from("function_calls".to_string()),
+        MeasurementValue(i64::try_from(cr.function_calls).unwrap()),
+    ));
 
     let measurements: HashMap<MeasurementUnit, MeasurementValue> = measurements
         .into_iter()
@@ -88,6 +119,7 @@ fn map_function_trace_to_sample(
     }
 }
 
+#[expect(clippy::too_many_arguments)]
 pub fn map_syscall_trace_to_sample(
     call_stack: Vec<FunctionCall>,
     invocations: i64,
@@ -96,25 +128,30 @@ pub fn map_syscall_trace_to_sample(
     calldata_factor: Option<usize>,
     calculate_l2_gas: bool,
     events: &HashMap<Vec<FunctionCall>, Vec<SummedUpEvent>>,
-) -> Sample {
-    let function_name = call_stack.last().unwrap().function_name();
-    let syscall_resources = versioned_constants
-        .os_resources
-        .execute_syscalls
-        .get(
-            &function_name
-                .0
-                .parse::<DeprecatedSyscallSelector>()
-                .expect(
-                    "Failed to map function to SyscallSelector.\n\n\
-                          This usually means that a syscall was encountered that the current cairo-profiler \
-                          version does not yet recognise. \n\
-                          Try updating cairo-profiler to the latest version, and if the issue persists, \
-                          consider opening a bug report: https://github.com/software-mansion/cairo-profiler/issues/new",
-                )
-            ,
-        )
+    messages: &HashMap<Vec<FunctionCall>, Vec<usize>>,
+) -> Result<Sample> {
+    // Storage syscalls may be followed by the accessed storage variable
+    let function_name = call_stack
+        .iter()
+        .rev()
+        .find_map(|call| match call {
+            FunctionCall::InternalFunctionCall(InternalFunctionCall::Syscall(function_name)) => {
+                Some(function_name)
+            }
+            _ => None,
+        })
         .unwrap();
+    let selector = function_name
+        .0
+        .parse::<DeprecatedSyscallSelector>()
+        .expect(
+            "Failed to map function to SyscallSelector.\n\n\
+                  This usually means that a syscall was encountered that the current cairo-profiler \
+                  version does not yet recognise. \n\
+                  Try updating cairo-profiler to the latest version, and if the issue persists, \
+                  consider opening a bug report: https://github.com/software-mansion/cairo-profiler/issues/new",
+        );
+    let syscall_resources = versioned_constants.syscall_resources(selector)?;
 
     let adjusted_resources = match syscall_resources {
         Unscaled(resources) => resources,
@@ -159,10 +196,21 @@ pub fn map_syscall_trace_to_sample(
         MeasurementValue(invocations),
     );
 
-    Sample {
+    if let Some(l1_data_gas) = syscall_l1_data_gas(selector, invocations) {
+        measurements.insert(
+            MeasurementUnit::from("l1_data_gas".to_string()),
+            MeasurementValue(l1_data_gas),
+        );
+    }
+
+    if let Some(message_sizes) = messages.get(&call_stack) {
+        add_l2_l1_messages_measurements(&mut measurements, message_sizes);
+    }
+
+    Ok(Sample {
         call_stack,
         measurements,
-    }
+    })
 }
 
 fn calculate_syscall_gas_measurements(
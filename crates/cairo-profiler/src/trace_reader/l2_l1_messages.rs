@@ -0,0 +1,69 @@
+//! Estimation of L1 gas spent on L2 -> L1 messages.
+//!
+//! Costs are not part of versioned constants - they follow Ethereum gas constants used by the
+//! sequencer: each message is logged by the Starknet core contract on L1 and is a part of the
+//! output of the proof verified by SHARP.
+use crate::trace_reader::sample::{MeasurementUnit, MeasurementValue};
+use std::collections::HashMap;
+
+/// Base gas cost of a log on L1.
+const GAS_PER_LOG: i64 = 375;
+/// Gas cost of a single log topic on L1.
+const GAS_PER_LOG_TOPIC: i64 = 375;
+/// Gas cost of a single word (32 bytes) of log data on L1.
+const GAS_PER_LOG_DATA_WORD: i64 = 8 * 32;
+/// Topics of the log of a message: the default one (event selector), sender and recipient.
+const MESSAGE_LOG_TOPICS: i64 = 3;
+/// Words of log data of a message besides its payload: the payload offset and length.
+const MESSAGE_LOG_ENCODED_DATA_WORDS: i64 = 2;
+/// Gas cost of verifying a single word of the proof output on L1.
+const SHARP_GAS_PER_MEMORY_WORD: i64 = 16 * 32 + 100;
+/// Words of the proof output of a message besides its payload: sender, recipient and payload length.
+const MESSAGE_HEADER_WORDS: i64 = 3;
+
+/// Returns L1 gas of sending messages with given payload sizes.
+pub fn l2_l1_messages_l1_gas(payload_sizes: &[usize]) -> i64 {
+    payload_sizes
+        .iter()
+        .map(|&payload_size| {
+            let payload_size =
+                i64::try_from(payload_size).expect("message payload size should fit in i64");
+            let log_gas = GAS_PER_LOG
+                + MESSAGE_LOG_TOPICS * GAS_PER_LOG_TOPIC
+                + (MESSAGE_LOG_ENCODED_DATA_WORDS + payload_size) * GAS_PER_LOG_DATA_WORD;
+            let proof_output_gas =
+                (MESSAGE_HEADER_WORDS + payload_size) * SHARP_GAS_PER_MEMORY_WORD;
+            log_gas + proof_output_gas
+        })
+        .sum()
+}
+
+/// Adds sizes and L1 gas of messages with given payload sizes to `measurements`, if there are any.
+pub fn add_l2_l1_messages_measurements(
+    measurements: &mut HashMap<MeasurementUnit, MeasurementValue>,
+    payload_sizes: &[usize],
+) {
+    if payload_sizes.is_empty() {
+        return;
+    }
+
+    for unit in ["l2_l1_message_sizes", "l1_gas"] {
+        assert!(!measurements.contains_key(&MeasurementUnit::from(unit.to_string())));
+    }
+
+    let summed_sizes: i64 = payload_sizes
+        .iter()
+        .sum::<usize>()
+        .try_into()
+        .expect("summed message sizes should fit in i64");
+    if summed_sizes > 0 {
+        measurements.insert(
+            MeasurementUnit::from("l2_l1_message_sizes".to_string()),
+            MeasurementValue(summed_sizes),
+        );
+    }
+    measurements.insert(
+        MeasurementUnit::from("l1_gas".to_string()),
+        MeasurementValue(l2_l1_messages_l1_gas(payload_sizes)),
+    );
+}
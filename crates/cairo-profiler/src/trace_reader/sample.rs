@@ -1,5 +1,6 @@
+use crate::trace_reader::l2_l1_messages::add_l2_l1_messages_measurements;
 use cairo_annotations::annotations::profiler::FunctionName;
-use cairo_annotations::trace_data::{ExecutionResources, L1Resources};
+use cairo_annotations::trace_data::ExecutionResources;
 use std::cmp::PartialEq;
 use std::collections::HashMap;
 use std::ops::Add;
@@ -10,7 +11,7 @@ pub(crate) struct Sample {
     pub measurements: HashMap<MeasurementUnit, MeasurementValue>,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
 pub enum FunctionCall {
     EntrypointCall(FunctionName),
     InternalFunctionCall(InternalFunctionCall),
@@ -27,12 +28,16 @@ impl FunctionCall {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
 pub enum InternalFunctionCall {
     Inlined(FunctionName),
     NonInlined(FunctionName),
     Syscall(FunctionName),
     Libfunc(FunctionName),
+    /// Storage variable accessed by the parent `StorageRead` or `StorageWrite` syscall.
+    StorageVariable(FunctionName),
+    /// Part of the transaction charged outside of its execution, e.g. its calldata or declared class.
+    TransactionData(FunctionName),
 }
 
 impl InternalFunctionCall {
@@ -41,12 +46,14 @@ impl InternalFunctionCall {
             InternalFunctionCall::Inlined(function_name)
             | InternalFunctionCall::NonInlined(function_name)
             | InternalFunctionCall::Syscall(function_name)
-            | InternalFunctionCall::Libfunc(function_name) => function_name,
+            | InternalFunctionCall::Libfunc(function_name)
+            | InternalFunctionCall::StorageVariable(function_name)
+            | InternalFunctionCall::TransactionData(function_name) => function_name,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct MeasurementUnit(pub String);
 
 impl From<String> for MeasurementUnit {
@@ -76,8 +83,9 @@ impl Sample {
     pub fn from(
         call_stack: Vec<FunctionCall>,
         resources: &ExecutionResources,
-        l1_resources: &L1Resources,
+        l2_l1_message_sizes: &[usize],
         l2_gas: Option<i64>,
+        l1_data_gas: Option<i64>,
     ) -> Self {
         let mut measurements: HashMap<MeasurementUnit, MeasurementValue> = vec![
             (
@@ -100,6 +108,10 @@ impl Sample {
                 MeasurementUnit::from("l2_gas".to_string()),
                 MeasurementValue(l2_gas.unwrap_or(0)),
             ),
+            (
+                MeasurementUnit::from("l1_data_gas".to_string()),
+                MeasurementValue(l1_data_gas.unwrap_or(0)),
+            ),
         ]
         .into_iter()
         .filter(|(_, value)| *value != 0)
@@ -113,23 +125,7 @@ impl Sample {
             );
         }
 
-        assert!(
-            !measurements.contains_key(&MeasurementUnit::from("l2_l1_message_sizes".to_string()))
-        );
-
-        l1_resources
-            .l2_l1_message_sizes
-            .iter()
-            .sum::<usize>()
-            .try_into()
-            .ok()
-            .filter(|summarized_resources| *summarized_resources > 0)
-            .map(|summarized_resources| {
-                measurements.insert(
-                    MeasurementUnit::from("l2_l1_message_sizes".to_string()),
-                    MeasurementValue(summarized_resources),
-                )
-            });
+        add_l2_l1_messages_measurements(&mut measurements, l2_l1_message_sizes);
 
         Sample {
             call_stack,
@@ -137,3 +133,25 @@ impl Sample {
         }
     }
 }
+
+/// Merges samples with identical call stacks into one, summing their measurements.
+/// Merged samples are ordered by the first appearance of their call stacks.
+pub fn aggregate_samples(samples: Vec<Sample>) -> Vec<Sample> {
+    let mut aggregated_samples: Vec<Sample> = vec![];
+    let mut indexes: HashMap<Vec<FunctionCall>, usize> = HashMap::new();
+
+    for sample in samples {
+        if let Some(&index) = indexes.get(&sample.call_stack) {
+            let measurements = &mut aggregated_samples[index].measurements;
+            for (unit, value) in sample.measurements {
+                let entry = measurements.entry(unit).or_insert(MeasurementValue(0));
+                *entry = entry.clone() + value;
+            }
+        } else {
+            indexes.insert(sample.call_stack.clone(), aggregated_samples.len());
+            aggregated_samples.push(sample);
+        }
+    }
+
+    aggregated_samples
+}
@@ -0,0 +1,65 @@
+//! Estimation of L1 data gas spent on publishing state diffs.
+//!
+//! Traces do not contain storage keys, so writes to the same key are not deduplicated and every
+//! write is counted as a separate storage update - the estimation is an upper bound.
+//! Nonce updates are not counted, as traces do not contain the transaction sender.
+use cairo_annotations::trace_data::{
+    CallEntryPoint, DeprecatedSyscallSelector, EntryPointType, ExecutionResources,
+};
+use starknet_types_core::felt::Felt;
+use std::collections::HashSet;
+
+/// Data gas needed to publish a single felt in a blob.
+const DATA_GAS_PER_FELT: i64 = 32;
+/// Felts published per storage update: the key and the new value.
+const STORAGE_UPDATE_FELTS: i64 = 2;
+/// Felts published per class hash update: the new class hash.
+const CLASS_HASH_UPDATE_FELTS: i64 = 1;
+/// Felts published once per modified contract: its address and a header with the nonce
+/// and the number of updates.
+const CONTRACT_HEADER_FELTS: i64 = 2;
+
+/// Returns L1 data gas of state updates made by the syscall, if it makes any.
+pub fn syscall_l1_data_gas(selector: DeprecatedSyscallSelector, invocations: i64) -> Option<i64> {
+    let felts = match selector {
+        DeprecatedSyscallSelector::StorageWrite => STORAGE_UPDATE_FELTS,
+        // Class hash of the deployed contract
+        DeprecatedSyscallSelector::Deploy | DeprecatedSyscallSelector::ReplaceClass => {
+            CLASS_HASH_UPDATE_FELTS
+        }
+        _ => return None,
+    };
+    Some(felts * invocations * DATA_GAS_PER_FELT)
+}
+
+/// Contracts which headers are already included in the state diff.
+#[derive(Default)]
+pub struct ModifiedContracts<'a>(HashSet<&'a Felt>);
+
+impl<'a> ModifiedContracts<'a> {
+    /// Returns L1 data gas of the header of the contract modified by the call, if the call
+    /// modifies the contract and the header was not charged by any previous call.
+    /// `own_resources` must not include resources of nested calls.
+    pub fn contract_header_l1_data_gas(
+        &mut self,
+        entry_point: &'a CallEntryPoint,
+        own_resources: &ExecutionResources,
+    ) -> Option<i64> {
+        let deployed = entry_point.entry_point_type == EntryPointType::Constructor;
+        let updated = own_resources
+            .syscall_counter
+            .as_ref()
+            .is_some_and(|syscall_counter| {
+                syscall_counter.keys().any(|&selector| {
+                    matches!(
+                        selector,
+                        DeprecatedSyscallSelector::StorageWrite
+                            | DeprecatedSyscallSelector::ReplaceClass
+                    )
+                })
+            });
+
+        ((deployed || updated) && self.0.insert(&entry_point.contract_address.0))
+            .then_some(CONTRACT_HEADER_FELTS * DATA_GAS_PER_FELT)
+    }
+}
@@ -0,0 +1,323 @@
+//! Incremental reading of call traces.
+//!
+//! VM traces are by far the largest part of trace files. Instead of deserializing the whole
+//! document at once, calls are profiled on function level as soon as they are read, and their
+//! VM traces are dropped right after, so at most one VM trace is kept in memory at a time.
+//! The rest of the trace and function level profiling infos of all calls are still kept in memory
+//! until samples are collected from them.
+use crate::cli::build_profile::BuildProfile;
+use crate::profiler_config::ProfilerConfig;
+use crate::sierra_loader::path_resolver::SierraPathResolver;
+use crate::sierra_loader::{CompiledArtifactsCache, PrecomputeConfig};
+use crate::trace_reader::function_name::ExternalTool;
+use crate::trace_reader::function_trace_builder::FunctionLevelProfilingInfo;
+use crate::trace_reader::{ProfiledTrace, profile_call_functions};
+use anyhow::{Context, Result, anyhow};
+use cairo_annotations::trace_data::{
+    CairoExecutionInfo, CallEntryPoint, CallTraceNode, CallTraceV1, ExecutionResources, L1Resources,
+};
+use serde::de::{self, DeserializeSeed, EnumAccess, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::io::{BufReader, Read};
+
+/// Reads the trace from `reader` and profiles each call on function level while reading.
+/// VM traces of all calls are dropped from the returned trace.
+///
+/// Requires the fields of each call to be in the order they are serialized by `cairo-annotations`
+/// (i.e. `entry_point` and `used_execution_resources` of the top-level call before its `nested_calls`).
+pub fn read_and_profile_trace(
+    reader: impl Read,
+    args: &BuildProfile,
+    path_resolver: SierraPathResolver,
+) -> Result<ProfiledTrace> {
+    let mut state = StreamingState {
+        args,
+        compiled_artifacts_cache: CompiledArtifactsCache::new(
+            path_resolver,
+            PrecomputeConfig::from(args),
+        ),
+        external_tool: None,
+        sierra_gas_tracking: None,
+        nested_calls_profiled: false,
+        function_level_profiling_infos: vec![],
+        error: None,
+    };
+
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let result = CallTraceSeed {
+        state: &mut state,
+        is_root: true,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|trace| deserializer.end().map(|()| trace));
+
+    if let Some(error) = state.error {
+        return Err(error);
+    }
+    let trace = result.context("Failed to deserialize call trace")?;
+
+    let cairo_enable_gas = root_cairo_enable_gas(&trace);
+    let external_tool = state
+        .external_tool
+        .context("Missing entry point of the top-level call in trace")?;
+
+    Ok(ProfiledTrace {
+        trace,
+        profiler_config: ProfilerConfig::new(args, cairo_enable_gas, external_tool),
+        compiled_artifacts_cache: state.compiled_artifacts_cache,
+        function_level_profiling_infos: state
+            .function_level_profiling_infos
+            .into_iter()
+            .flatten()
+            .collect(),
+    })
+}
+
+struct StreamingState<'a> {
+    args: &'a BuildProfile,
+    compiled_artifacts_cache: CompiledArtifactsCache,
+    /// Known once the entry point of the top-level call is read.
+    external_tool: Option<ExternalTool>,
+    /// Known once the resources of the top-level call are read.
+    sierra_gas_tracking: Option<bool>,
+    /// Whether any call other than the top-level one was profiled.
+    nested_calls_profiled: bool,
+    /// Indexed by the position of a call in depth-first, pre-order traversal of the trace.
+    /// Calls without Cairo execution info have no profiling info.
+    function_level_profiling_infos: Vec<Option<FunctionLevelProfilingInfo>>,
+    /// Errors are kept here, so they are not reduced to a message by `serde`.
+    error: Option<anyhow::Error>,
+}
+
+impl StreamingState<'_> {
+    fn profile_call(
+        &mut self,
+        call: &CallTraceV1,
+        is_root: bool,
+    ) -> Result<FunctionLevelProfilingInfo> {
+        let (Some(external_tool), Some(sierra_gas_tracking)) =
+            (self.external_tool, self.sierra_gas_tracking)
+        else {
+            return Err(anyhow!(
+                "Cannot read the trace incrementally: `entry_point` and `used_execution_resources` \
+                 of the top-level call must precede its `nested_calls`"
+            ));
+        };
+        let cairo_execution_info = call
+            .cairo_execution_info
+            .as_ref()
+            .expect("Only calls with Cairo execution info can be profiled on function level");
+
+        // Like when the trace is read at once, `enable_gas` of the top-level call applies to all
+        // calls. It is serialized after the nested calls though, so they are profiled assuming
+        // the default, and the assumption is verified once the top-level call is read.
+        let cairo_enable_gas = if is_root {
+            let cairo_enable_gas = root_cairo_enable_gas(call);
+            if !cairo_enable_gas && self.nested_calls_profiled {
+                return Err(anyhow!(
+                    "Cannot read the trace incrementally: `enable_gas` of the top-level call is \
+                     disabled, which is known only after its nested calls are profiled. \
+                     Build the profile without `--stream`"
+                ));
+            }
+            cairo_enable_gas
+        } else {
+            self.nested_calls_profiled = true;
+            true
+        };
+
+        self.compiled_artifacts_cache
+            .add_sierra_program(&cairo_execution_info.source_sierra_path, cairo_enable_gas)?;
+
+        profile_call_functions(
+            call,
+            &self.compiled_artifacts_cache,
+            &ProfilerConfig::new(self.args, cairo_enable_gas, external_tool),
+            sierra_gas_tracking,
+        )
+    }
+
+    fn fail<E: de::Error>(&mut self, error: anyhow::Error) -> E {
+        let message = format!("{error:#}");
+        self.error = Some(error);
+        E::custom(message)
+    }
+}
+
+fn root_cairo_enable_gas(trace: &CallTraceV1) -> bool {
+    trace
+        .cairo_execution_info
+        .as_ref()
+        .and_then(|info| info.enable_gas)
+        .unwrap_or(true)
+}
+
+struct CallTraceSeed<'s, 'a> {
+    state: &'s mut StreamingState<'a>,
+    is_root: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for CallTraceSeed<'_, '_> {
+    type Value = CallTraceV1;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for CallTraceSeed<'_, '_> {
+    type Value = CallTraceV1;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a call trace")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let state = self.state;
+        let call_idx = state.function_level_profiling_infos.len();
+        state.function_level_profiling_infos.push(None);
+
+        let mut entry_point: Option<CallEntryPoint> = None;
+        let mut cumulative_resources: Option<ExecutionResources> = None;
+        let mut used_l1_resources: Option<L1Resources> = None;
+        let mut nested_calls: Option<Vec<CallTraceNode>> = None;
+        let mut cairo_execution_info: Option<CairoExecutionInfo> = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "entry_point" => {
+                    let value: CallEntryPoint = map.next_value()?;
+                    if self.is_root {
+                        let external_tool =
+                            ExternalTool::from_contract_prefix(value.contract_name.as_deref())
+                                .map_err(|error| state.fail::<A::Error>(error))?;
+                        state.external_tool = Some(external_tool);
+                    }
+                    entry_point = Some(value);
+                }
+                "used_execution_resources" => {
+                    let value: ExecutionResources = map.next_value()?;
+                    if self.is_root {
+                        state.sierra_gas_tracking =
+                            Some(value.gas_consumed.unwrap_or_default() > 0);
+                    }
+                    cumulative_resources = Some(value);
+                }
+                "used_l1_resources" => used_l1_resources = Some(map.next_value()?),
+                "nested_calls" => {
+                    nested_calls =
+                        Some(map.next_value_seed(NestedCallsSeed { state: &mut *state })?);
+                }
+                "cairo_execution_info" => cairo_execution_info = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let mut call = CallTraceV1 {
+            entry_point: entry_point.ok_or_else(|| de::Error::missing_field("entry_point"))?,
+            cumulative_resources: cumulative_resources
+                .ok_or_else(|| de::Error::missing_field("used_execution_resources"))?,
+            used_l1_resources: used_l1_resources
+                .ok_or_else(|| de::Error::missing_field("used_l1_resources"))?,
+            nested_calls: nested_calls.ok_or_else(|| de::Error::missing_field("nested_calls"))?,
+            cairo_execution_info,
+        };
+
+        if call.cairo_execution_info.is_some() {
+            let info = state
+                .profile_call(&call, self.is_root)
+                .map_err(|error| state.fail::<A::Error>(error))?;
+            state.function_level_profiling_infos[call_idx] = Some(info);
+
+            if let Some(cairo_execution_info) = &mut call.cairo_execution_info {
+                cairo_execution_info.casm_level_info.vm_trace = Vec::new();
+            }
+        }
+
+        Ok(call)
+    }
+}
+
+struct NestedCallsSeed<'s, 'a> {
+    state: &'s mut StreamingState<'a>,
+}
+
+impl<'de> DeserializeSeed<'de> for NestedCallsSeed<'_, '_> {
+    type Value = Vec<CallTraceNode>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NestedCallsSeed<'_, '_> {
+    type Value = Vec<CallTraceNode>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of nested calls")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut nested_calls = vec![];
+        while let Some(node) = seq.next_element_seed(CallTraceNodeSeed {
+            state: &mut *self.state,
+        })? {
+            nested_calls.push(node);
+        }
+        Ok(nested_calls)
+    }
+}
+
+struct CallTraceNodeSeed<'s, 'a> {
+    state: &'s mut StreamingState<'a>,
+}
+
+const CALL_TRACE_NODE_VARIANTS: &[&str] = &["EntryPointCall", "DeployWithoutConstructor"];
+
+impl<'de> DeserializeSeed<'de> for CallTraceNodeSeed<'_, '_> {
+    type Value = CallTraceNode;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_enum("CallTraceNode", CALL_TRACE_NODE_VARIANTS, self)
+    }
+}
+
+impl<'de> Visitor<'de> for CallTraceNodeSeed<'_, '_> {
+    type Value = CallTraceNode;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a call trace node")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (variant, access) = data.variant::<String>()?;
+        match variant.as_str() {
+            "EntryPointCall" => {
+                let call = de::VariantAccess::newtype_variant_seed(
+                    access,
+                    CallTraceSeed {
+                        state: self.state,
+                        is_root: false,
+                    },
+                )?;
+                Ok(CallTraceNode::EntryPointCall(Box::new(call)))
+            }
+            "DeployWithoutConstructor" => {
+                de::VariantAccess::unit_variant(access)?;
+                Ok(CallTraceNode::DeployWithoutConstructor)
+            }
+            other => Err(de::Error::unknown_variant(other, CALL_TRACE_NODE_VARIANTS)),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Estimation of L2 gas spent on parts of transactions other than their execution, which traces
+//! do not describe directly: constructor calldata of `DEPLOY_ACCOUNT` transactions and classes
+//! declared by `DECLARE` transactions.
+use crate::sierra_loader::CompiledArtifactsCache;
+use crate::trace_reader::sample::{
+    FunctionCall, InternalFunctionCall, MeasurementUnit, MeasurementValue, Sample,
+};
+use crate::versioned_constants_reader::VersionedConstants;
+use anyhow::Result;
+use cairo_annotations::annotations::profiler::FunctionName;
+use cairo_annotations::trace_data::{CallEntryPoint, CallTraceNode, CallTraceV1, EntryPointType};
+use camino::Utf8PathBuf;
+use std::collections::{HashMap, HashSet};
+
+/// Returns the sample of calldata of the constructor, treating it as executed by a `DEPLOY_ACCOUNT`
+/// transaction, or `None` if the entrypoint is not a constructor.
+/// `call_stack` must end with the constructor.
+pub fn deploy_account_sample(
+    call_stack: &[FunctionCall],
+    entry_point: &CallEntryPoint,
+    versioned_constants: &VersionedConstants,
+) -> Result<Option<Sample>> {
+    if entry_point.entry_point_type != EntryPointType::Constructor {
+        return Ok(None);
+    }
+
+    let data_size: u64 = (entry_point.calldata_len.unwrap_or_default()
+        + entry_point.signature_len.unwrap_or_default())
+    .try_into()?;
+    let l2_gas: i64 = (versioned_constants
+        .archival_data_gas_costs
+        .gas_per_data_felt
+        * data_size)
+        .to_integer()
+        .try_into()?;
+
+    Ok(Some(transaction_data_sample(
+        call_stack,
+        "DEPLOY_ACCOUNT calldata".to_string(),
+        l2_gas,
+    )))
+}
+
+/// Contract classes whose declarations are already charged.
+pub struct DeclaredClasses<'a> {
+    compiled_artifacts_cache: &'a CompiledArtifactsCache,
+    sierra_paths: HashSet<Utf8PathBuf>,
+}
+
+impl<'a> DeclaredClasses<'a> {
+    pub fn new(compiled_artifacts_cache: &'a CompiledArtifactsCache) -> Self {
+        Self {
+            compiled_artifacts_cache,
+            sierra_paths: HashSet::new(),
+        }
+    }
+
+    /// Returns samples of declarations of contract classes executed by the transaction, which
+    /// were not declared before. `call_stack` must end with the transaction entrypoint.
+    pub fn declare_samples(
+        &mut self,
+        call_stack: &[FunctionCall],
+        trace: &CallTraceV1,
+        versioned_constants: &VersionedConstants,
+    ) -> Result<Vec<Sample>> {
+        let mut class_sizes = vec![];
+        self.collect_undeclared_class_sizes(trace, &mut class_sizes)?;
+
+        class_sizes
+            .into_iter()
+            .map(|(contract_name, class_size)| {
+                let l2_gas: i64 = (versioned_constants
+                    .archival_data_gas_costs
+                    .gas_per_code_byte
+                    * class_size)
+                    .to_integer()
+                    .try_into()?;
+                Ok(transaction_data_sample(
+                    call_stack,
+                    format!("DECLARE {contract_name}"),
+                    l2_gas,
+                ))
+            })
+            .collect()
+    }
+
+    fn collect_undeclared_class_sizes(
+        &mut self,
+        trace: &CallTraceV1,
+        class_sizes: &mut Vec<(String, u64)>,
+    ) -> Result<()> {
+        if let Some(cairo_execution_info) = &trace.cairo_execution_info {
+            let sierra_path = self
+                .compiled_artifacts_cache
+                .resolve_sierra_path(&cairo_execution_info.source_sierra_path)?;
+            let compiled_artifacts = self
+                .compiled_artifacts_cache
+                .get_compiled_artifacts_for_path(&sierra_path);
+
+            if let Some(class_size) = compiled_artifacts.declared_class_size {
+                let contract_name = trace.entry_point.contract_name.clone().unwrap_or_else(|| {
+                    sierra_path
+                        .file_name()
+                        .unwrap_or(sierra_path.as_str())
+                        .to_string()
+                });
+                if self.sierra_paths.insert(sierra_path) {
+                    class_sizes.push((contract_name, class_size.try_into()?));
+                }
+            }
+        }
+
+        for sub_trace_node in &trace.nested_calls {
+            if let CallTraceNode::EntryPointCall(sub_trace) = sub_trace_node {
+                self.collect_undeclared_class_sizes(sub_trace, class_sizes)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn transaction_data_sample(call_stack: &[FunctionCall], name: String, l2_gas: i64) -> Sample {
+    let mut call_stack = call_stack.to_vec();
+    call_stack.push(FunctionCall::InternalFunctionCall(
+        InternalFunctionCall::TransactionData(FunctionName(name)),
+    ));
+
+    Sample {
+        call_stack,
+        measurements: HashMap::from([(
+            MeasurementUnit::from("l2_gas".to_string()),
+            MeasurementValue(l2_gas),
+        )]),
+    }
+}
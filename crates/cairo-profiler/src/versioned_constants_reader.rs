@@ -1,19 +1,88 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use cairo_annotations::trace_data::{DeprecatedSyscallSelector, VmExecutionResources};
 use camino::Utf8PathBuf;
+use clap::ValueEnum;
 use num_rational::Ratio;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 
 pub type ResourceCost = Ratio<u64>;
 
+/// Starknet versions, which versioned constants are embedded in the binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StarknetVersion {
+    #[value(name = "0.13.3")]
+    V0_13_3,
+    #[value(name = "0.13.4")]
+    V0_13_4,
+    #[value(name = "0.13.5")]
+    V0_13_5,
+    #[value(name = "0.13.6")]
+    V0_13_6,
+    #[value(name = "0.14.0")]
+    V0_14_0,
+    #[value(name = "0.14.1")]
+    V0_14_1,
+}
+
+impl StarknetVersion {
+    /// Version used when neither a version nor a versioned constants file is specified.
+    pub const DEFAULT: Self = Self::V0_14_1;
+
+    fn versioned_constants(self) -> &'static str {
+        match self {
+            Self::V0_13_3 => include_str!("../resources/versioned_constants_0_13_3.json"),
+            Self::V0_13_4 => include_str!("../resources/versioned_constants_0_13_4.json"),
+            Self::V0_13_5 => include_str!("../resources/versioned_constants_0_13_5.json"),
+            Self::V0_13_6 => include_str!("../resources/versioned_constants_0_13_6.json"),
+            Self::V0_14_0 => include_str!("../resources/versioned_constants_0_14_0.json"),
+            Self::V0_14_1 => include_str!("../resources/versioned_constants_0_14_1.json"),
+        }
+    }
+}
+
+impl fmt::Display for StarknetVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self
+            .to_possible_value()
+            .expect("Starknet versions should not be skipped");
+        write!(f, "{}", value.get_name())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VersionedConstants {
     pub os_resources: OsResources,
     pub os_constants: OsConstants,
     pub archival_data_gas_costs: ArchivalDataGasCosts,
+    /// Version of the embedded constants, `None` if they were read from a file.
+    #[serde(skip)]
+    pub starknet_version: Option<StarknetVersion>,
+}
+
+impl VersionedConstants {
+    /// Returns resources used by the syscall. Embedded constants of older Starknet versions do not
+    /// know syscalls introduced later, so using them is an error.
+    pub fn syscall_resources(
+        &self,
+        selector: DeprecatedSyscallSelector,
+    ) -> Result<&SyscallVariant> {
+        self.os_resources
+            .execute_syscalls
+            .get(&selector)
+            .ok_or_else(|| match self.starknet_version {
+                Some(starknet_version) => anyhow!(
+                    "Versioned constants of Starknet {starknet_version} do not contain the cost of {selector:?} syscall. \
+                     It was probably introduced in a newer Starknet version - select one with `--starknet-version`"
+                ),
+                None => anyhow!(
+                    "Versioned constants do not contain the cost of {selector:?} syscall"
+                ),
+            })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,6 +90,8 @@ pub struct OsConstants {
     pub step_gas_cost: u64,
     pub memory_hole_gas_cost: u64,
     pub syscall_base_gas_cost: SyscallBaseGasCost,
+    /// Missing in versions older than 0.13.4, which do not charge sierra gas for builtins.
+    #[serde(default)]
     pub builtin_gas_costs: BuiltinGasCosts,
 }
 
@@ -68,16 +139,23 @@ pub struct ArchivalDataGasCosts {
     pub gas_per_code_byte: ResourceCost,
 }
 
-/// Reads and parses the resource map file at given path
-/// It also checks that the file have cost information about all required libfuncs (syscalls)
+/// Reads and parses the resource map file at given path, or the one embedded for `starknet_version`
+/// if the path is not provided.
+/// It also checks that the file have cost information about all required libfuncs (syscalls).
+/// Embedded files of older Starknet versions are exempt from the check, as they do not know
+/// syscalls introduced later - syscalls missing from them are reported once they are found in the trace.
 pub fn read_and_parse_versioned_constants_file(
     path: Option<&Utf8PathBuf>,
+    starknet_version: Option<StarknetVersion>,
 ) -> Result<VersionedConstants> {
     let file_content = match path {
         Some(path) => fs::read_to_string(path).with_context(|| {
             format!("Cannot read versioned constants file at specified path {path}")
         })?,
-        None => include_str!("../resources/versioned_constants_0_14_1.json").to_string(),
+        None => starknet_version
+            .unwrap_or(StarknetVersion::DEFAULT)
+            .versioned_constants()
+            .to_string(),
     };
     let json_value: Value = serde_json::from_str(&file_content)
         .context("Failed to parse versioned constants file content")?;
@@ -94,17 +172,19 @@ pub fn read_and_parse_versioned_constants_file(
     let os_resources: OsResources = serde_json::from_value(parsed_os_resources.clone())
         .context("Failed to deserialize 'os_resources' field into OsResources struct")?;
 
-    let missing_libfuncs: Vec<_> = DeprecatedSyscallSelector::all()
-        .iter()
-        .filter(|&syscall| !os_resources.execute_syscalls.contains_key(syscall))
-        .copied()
-        .collect();
-
-    if !missing_libfuncs.is_empty() {
-        return Err(anyhow::anyhow!(
-            "Missing libfuncs cost in versioned constants file: {missing_libfuncs:?}.\n\
-            Make sure to include costs of these libfuncs in the aforementioned file."
-        ));
+    if path.is_some() {
+        let missing_libfuncs: Vec<_> = DeprecatedSyscallSelector::all()
+            .iter()
+            .filter(|&syscall| !os_resources.execute_syscalls.contains_key(syscall))
+            .copied()
+            .collect();
+
+        if !missing_libfuncs.is_empty() {
+            return Err(anyhow!(
+                "Missing libfuncs cost in versioned constants file: {missing_libfuncs:?}.\n\
+                Make sure to include costs of these libfuncs in the aforementioned file."
+            ));
+        }
     }
 
     let parsed_archival_data_gas_cost = json_value
@@ -119,5 +199,8 @@ pub fn read_and_parse_versioned_constants_file(
         os_resources,
         os_constants,
         archival_data_gas_costs,
+        starknet_version: path
+            .is_none()
+            .then(|| starknet_version.unwrap_or(StarknetVersion::DEFAULT)),
     })
 }
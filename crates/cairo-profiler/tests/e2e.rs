@@ -1,7 +1,8 @@
 use assert_fs::fixture::PathCopy;
-use indoc::indoc;
+use indoc::{formatdoc, indoc};
 use snapbox::cargo_bin;
 use snapbox::cmd::Command as SnapboxCommand;
+use std::fs;
 use std::str;
 use test_case::test_case;
 
@@ -34,9 +35,15 @@ fn output_path() {
 #[test_case(&["call.json", "--show-inlined-functions"]; "with inlined functions")]
 #[test_case(&["call.json", "--split-generics"]; "with split generics")]
 #[test_case(&["call.json", "--max-function-stack-trace-depth", "5"]; "with max function trace depth")]
+#[test_case(&["call.json", "--collapse-recursion"]; "with collapsed recursion")]
 #[test_case(&["call.json", "--show-details"]; "with details")]
 #[test_case(&["call.json"]; "without details")]
 #[test_case(&["call.json", "--versioned-constants-path", "test_versioned_constants.json"]; "with custom versioned constants file")]
+#[test_case(&["call.json", "--starknet-version", "0.13.3"]; "with starknet 0.13.3")]
+#[test_case(&["call.json", "--starknet-version", "0.13.4"]; "with starknet 0.13.4")]
+#[test_case(&["call.json", "--starknet-version", "0.13.5"]; "with starknet 0.13.5")]
+#[test_case(&["call.json", "--starknet-version", "0.13.6"]; "with starknet 0.13.6")]
+#[test_case(&["call.json", "--starknet-version", "0.14.0"]; "with starknet 0.14.0")]
 fn simple_package(args: &[&str]) {
     let project_root = project_root::get_project_root().unwrap();
     let temp_dir = assert_fs::TempDir::new().unwrap();
@@ -119,6 +126,50 @@ fn missing_os_constants_from_versioned_constants_file() {
         ));
 }
 
+#[test]
+fn syscall_missing_from_embedded_versioned_constants() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root.join("crates/cairo-profiler/tests/contracts/other_syscalls/precompiled/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // `Sha512ProcessBlock` was introduced after Starknet 0.13.4
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("trace_other_syscalls.json")
+        .args(["--starknet-version", "0.13.4"])
+        .assert()
+        .failure()
+        .stderr_eq(indoc!(
+            r"
+            Error: Versioned constants of Starknet 0.13.4 do not contain the cost of Sha512ProcessBlock syscall. It was probably introduced in a newer Starknet version - select one with `--starknet-version`
+            "
+        ));
+}
+
+#[test]
+fn constants_list() {
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .args(["constants", "list"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r"
+            0.13.3
+            0.13.4
+            0.13.5
+            0.13.6
+            0.14.0
+            0.14.1 (default)
+            "
+        ));
+}
+
 #[test]
 fn view_samples() {
     let project_root = project_root::get_project_root().unwrap();
@@ -257,15 +308,15 @@ fn view_range_check_builtin() {
         .stdout_eq(indoc!(
             r#"
 
-            Showing nodes accounting for 42 range check builtin, 100.00% of 42 range check builtin total
+            Showing nodes accounting for 35 range check builtin, 83.33% of 42 range check builtin total
             Showing top 4 nodes out of 15
             
-                               flat |  flat% |    sum% |                    cum |    cum% |  
-            ------------------------+--------+---------+------------------------+---------+-----------------------------------------------------------------------
-             21 range check builtin | 50.00% |  50.00% | 42 range check builtin | 100.00% | "Contract: SNFORGE_TEST_CODE/nFunction: SNFORGE_TEST_CODE_FUNCTION/n" 
-             18 range check builtin | 42.86% |  92.86% | 21 range check builtin |  50.00% | "CallContract" 
-              2 range check builtin |  4.76% |  97.62% |  3 range check builtin |   7.14% | "Contract: HelloStarknet/nFunction: get_balance/n" 
-              1 range check builtin |  2.38% | 100.00% |  1 range check builtin |   2.38% | "StorageRead" 
+                               flat |  flat% |   sum% |                    cum |    cum% |  
+            ------------------------+--------+--------+------------------------+---------+-----------------------------------------------------------------------
+             18 range check builtin | 42.86% | 42.86% | 21 range check builtin |  50.00% | "CallContract" 
+              6 range check builtin | 14.29% | 57.14% | 42 range check builtin | 100.00% | "Contract: SNFORGE_TEST_CODE/nFunction: SNFORGE_TEST_CODE_FUNCTION/n" 
+              6 range check builtin | 14.29% | 71.43% |  6 range check builtin |  14.29% | "snforge_std::cheatcode::execute_cheatcode" 
+              5 range check builtin | 11.90% | 83.33% |  5 range check builtin |  11.90% | "core::array::SpanFelt252Serde::deserialize" 
             "#
         ));
 }
@@ -962,7 +1013,7 @@ fn tree_more_nested_calls_than_triggers_missing_call_contract() {
             [WARNING] The trace file does not contain either one of calldata_len, signature_len or events_summary. This may lead to inaccurate l2 gas measurements. Consider using `snforge` >= `0.49.0`.
             [ERROR] There are no syscalls left in the program trace, but at least one unhandled call in trace file CallEntryPoint { class_hash: Some(ClassHash(0x117)), entry_point_type: External, entry_point_selector: EntryPointSelector(0x17340c6779204ea2a91c87d1c2226a3aebda65c64da3672a36893c4330ea27b), contract_address: ContractAddress(0x1724987234973219347210837402), call_type: Call, contract_name: Some("SNFORGE_TEST_CODE"), function_name: Some("SNFORGE_TEST_CODE_FUNCTION"), calldata_len: Some(0), events_summary: None, signature_len: None }!
             
-            thread 'main' ([..]) panicked at crates/cairo-profiler/src/trace_reader.rs:270:13:
+            thread 'main' ([..]) panicked at crates/cairo-profiler/src/trace_reader.rs:447:13:
             Too many EntryPointCalls for triggers
             note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
             "#
@@ -992,7 +1043,7 @@ fn tree_more_triggers_than_nested_calls() {
             [WARNING] The trace file does not contain either one of calldata_len, signature_len or events_summary. This may lead to inaccurate l2 gas measurements. Consider using `snforge` >= `0.49.0`.
             [ERROR] Found syscall CallContract in the program trace, that do not have corresponding calls in trace file!
             
-            thread 'main' ([..]) panicked at crates/cairo-profiler/src/trace_reader.rs:216:17:
+            thread 'main' ([..]) panicked at crates/cairo-profiler/src/trace_reader.rs:387:17:
             Too few EntryPointCalls for triggers
             note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
             "
@@ -1022,7 +1073,7 @@ fn tree_mismatched_syscall_with_entrypoint() {
             [WARNING] The trace file does not contain either one of calldata_len, signature_len or events_summary. This may lead to inaccurate l2 gas measurements. Consider using `snforge` >= `0.49.0`.
             [ERROR] Found syscall CallContract in the program trace, that do not corresponds to the next call from trace file CallEntryPoint { class_hash: Some(ClassHash(0x117)), entry_point_type: External, entry_point_selector: EntryPointSelector(0x17340c6779204ea2a91c87d1c2226a3aebda65c64da3672a36893c4330ea27b), contract_address: ContractAddress(0x1724987234973219347210837402), call_type: Call, contract_name: Some("SNFORGE_TEST_CODE"), function_name: Some("SNFORGE_TEST_CODE_FUNCTION"), calldata_len: Some(0), events_summary: None, signature_len: None }!
             
-            thread 'main' ([..]) panicked at crates/cairo-profiler/src/trace_reader.rs:260:17:
+            thread 'main' ([..]) panicked at crates/cairo-profiler/src/trace_reader.rs:437:17:
             Trigger does not match entrypoint
             note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
             "#
@@ -1562,13 +1613,13 @@ fn view_execute_simple_standalone_mem_holes() {
         .stdout_eq(indoc!(
             r#"
             
-            Showing nodes accounting for 62 memory holes, 100.00% of 62 memory holes total
+            Showing nodes accounting for 55 memory holes, 88.71% of 62 memory holes total
             Showing top 2 nodes out of 8
             
-                        flat |   flat% |    sum% |             cum |    cum% |  
-            -----------------+---------+---------+-----------------+---------+----------------------------------------------------------
-             62 memory holes | 100.00% | 100.00% | 62 memory holes | 100.00% | "SCARB_EXECUTE/nTarget: standalone/nFunction: <unknown>" 
-              0 memory holes |   0.00% | 100.00% |  0 memory holes |   0.00% | "core::array::serialize_array_helper" 
+                        flat |  flat% |   sum% |             cum |   cum% |  
+            -----------------+--------+--------+-----------------+--------+------------------------------------------------------------------
+             43 memory holes | 69.35% | 69.35% | 49 memory holes | 79.03% | "core::byte_array::ByteArrayImpl::append_word" 
+             12 memory holes | 19.35% | 88.71% | 12 memory holes | 19.35% | "core::to_byte_array::append_formatted_to_byte_array[1509-1662]" 
             "#
         ));
 }
@@ -1610,13 +1661,13 @@ fn view_execute_simple_standalone_range_check() {
         .stdout_eq(indoc!(
             r#"
             
-            Showing nodes accounting for 11 range check builtin, 100.00% of 11 range check builtin total
+            Showing nodes accounting for 7 range check builtin, 63.64% of 11 range check builtin total
             Showing top 2 nodes out of 8
             
-                               flat |   flat% |    sum% |                    cum |    cum% |  
-            ------------------------+---------+---------+------------------------+---------+----------------------------------------------------------
-             11 range check builtin | 100.00% | 100.00% | 11 range check builtin | 100.00% | "SCARB_EXECUTE/nTarget: standalone/nFunction: <unknown>" 
-              0 range check builtin |   0.00% | 100.00% |  0 range check builtin |   0.00% | "core::array::serialize_array_helper" 
+                              flat |  flat% |   sum% |                   cum |   cum% |  
+            -----------------------+--------+--------+-----------------------+--------+-----------------------------------------------------------------
+             4 range check builtin | 36.36% | 36.36% | 4 range check builtin | 36.36% | "core::to_byte_array::append_formatted_to_byte_array[782-1099]" 
+             3 range check builtin | 27.27% | 63.64% | 4 range check builtin | 36.36% | "core::byte_array::ByteArrayImpl::append_word" 
             "#
         ));
 }
@@ -1733,13 +1784,13 @@ fn view_execute_simple_bootloader_mem_holes() {
         .stdout_eq(indoc!(
             r#"
             
-            Showing nodes accounting for 61 memory holes, 100.00% of 61 memory holes total
+            Showing nodes accounting for 55 memory holes, 90.16% of 61 memory holes total
             Showing top 2 nodes out of 8
             
-                        flat |   flat% |    sum% |             cum |    cum% |  
-            -----------------+---------+---------+-----------------+---------+----------------------------------------------------------
-             61 memory holes | 100.00% | 100.00% | 61 memory holes | 100.00% | "SCARB_EXECUTE/nTarget: bootloader/nFunction: <unknown>" 
-              0 memory holes |   0.00% | 100.00% |  0 memory holes |   0.00% | "core::array::serialize_array_helper" 
+                        flat |  flat% |   sum% |             cum |   cum% |  
+            -----------------+--------+--------+-----------------+--------+------------------------------------------------------------------
+             43 memory holes | 70.49% | 70.49% | 49 memory holes | 80.33% | "core::byte_array::ByteArrayImpl::append_word" 
+             12 memory holes | 19.67% | 90.16% | 12 memory holes | 19.67% | "core::to_byte_array::append_formatted_to_byte_array[1509-1662]" 
             "#
         ));
 }
@@ -1781,13 +1832,13 @@ fn view_execute_simple_bootloader_range_check() {
         .stdout_eq(indoc!(
             r#"
             
-            Showing nodes accounting for 11 range check builtin, 100.00% of 11 range check builtin total
+            Showing nodes accounting for 7 range check builtin, 63.64% of 11 range check builtin total
             Showing top 2 nodes out of 8
             
-                               flat |   flat% |    sum% |                    cum |    cum% |  
-            ------------------------+---------+---------+------------------------+---------+----------------------------------------------------------
-             11 range check builtin | 100.00% | 100.00% | 11 range check builtin | 100.00% | "SCARB_EXECUTE/nTarget: bootloader/nFunction: <unknown>" 
-              0 range check builtin |   0.00% | 100.00% |  0 range check builtin |   0.00% | "core::array::serialize_array_helper" 
+                              flat |  flat% |   sum% |                   cum |   cum% |  
+            -----------------------+--------+--------+-----------------------+--------+-----------------------------------------------------------------
+             4 range check builtin | 36.36% | 36.36% | 4 range check builtin | 36.36% | "core::to_byte_array::append_formatted_to_byte_array[782-1099]" 
+             3 range check builtin | 27.27% | 63.64% | 4 range check builtin | 36.36% | "core::byte_array::ByteArrayImpl::append_word" 
             "#
         ));
 }
@@ -2311,3 +2362,1929 @@ fn view_different_syscalls() {
         "#
         ));
 }
+
+#[test_case("crates/cairo-profiler/tests/contracts/balance_simple/precompiled_sierra_gas/", "trace_balance_simple.json", "sierra gas"; "sierra_gas")]
+#[test_case("crates/cairo-profiler/tests/contracts/balance_simple/precompiled_cairo_steps/", "trace_balance_simple_fork.json", "steps"; "cairo_steps_fork")]
+#[test_case("crates/cairo-profiler/tests/executable_programs/multiple_targets/precompiled/", "bootloader_with_syscalls_trace.json", "sierra gas"; "execute_bootloader")]
+fn streamed_trace_gives_same_profile(trace_dir: &str, trace_name: &str, sample: &str) {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(project_root.join(trace_dir), &["*.json"])
+        .unwrap();
+
+    for (output, stream) in [("profile.pb.gz", false), ("streamed.pb.gz", true)] {
+        let mut command = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("build-profile")
+            .arg(trace_name)
+            .args(["--output-path", output]);
+        if stream {
+            command = command.arg("--stream");
+        }
+        command.assert().success();
+    }
+
+    let view = |profile: &str| {
+        SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("view")
+            .arg(profile)
+            .args(["--limit", "2137", "--sample", sample])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+
+    assert_eq!(
+        str::from_utf8(&view("profile.pb.gz")).unwrap(),
+        str::from_utf8(&view("streamed.pb.gz")).unwrap()
+    );
+}
+
+#[test_case("gzip", false; "gzip")]
+#[test_case("zstd", false; "zstd")]
+#[test_case("none", true; "stdin")]
+#[test_case("gzip", true; "gzip_stdin")]
+fn compressed_trace_gives_same_profile(compression: &str, from_stdin: bool) {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root.join(
+                "crates/cairo-profiler/tests/contracts/balance_simple/precompiled_sierra_gas/",
+            ),
+            &["*.json"],
+        )
+        .unwrap();
+
+    let trace = std::fs::read(temp_dir.join("trace_balance_simple.json")).unwrap();
+    let compressed_trace = match compression {
+        "gzip" => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            std::io::Write::write_all(&mut encoder, &trace).unwrap();
+            encoder.finish().unwrap()
+        }
+        "zstd" => zstd::encode_all(trace.as_slice(), 0).unwrap(),
+        _ => trace,
+    };
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("trace_balance_simple.json")
+        .assert()
+        .success();
+
+    let command = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .args(["--output-path", "compressed.pb.gz"]);
+    let command = if from_stdin {
+        command.arg("-").stdin(compressed_trace)
+    } else {
+        std::fs::write(temp_dir.join("trace.json.compressed"), compressed_trace).unwrap();
+        command.arg("trace.json.compressed")
+    };
+    command.assert().success();
+
+    let view = |profile: &str| {
+        SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("view")
+            .arg(profile)
+            .args(["--limit", "2137", "--sample", "sierra gas"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+
+    assert_eq!(
+        str::from_utf8(&view("profile.pb.gz")).unwrap(),
+        str::from_utf8(&view("compressed.pb.gz")).unwrap()
+    );
+}
+
+#[test_case(&["--remap-path-prefix", "/ci/build/target/dev=artifacts"]; "remap_path_prefix")]
+#[test_case(&["--remap-path-prefix", "/ci=nonexistent", "--remap-path-prefix", "/ci/build=artifacts/../artifacts/build"]; "last_remap_wins")]
+#[test_case(&["--sierra-search-dir", "nonexistent", "--sierra-search-dir", "artifacts"]; "sierra_search_dir")]
+#[test_case(&["--sierra-search-dir", "artifacts", "--stream"]; "sierra_search_dir_stream")]
+fn trace_from_another_machine(args: &[&str]) {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root.join(
+                "crates/cairo-profiler/tests/contracts/balance_simple/precompiled_sierra_gas/",
+            ),
+            &["*.json"],
+        )
+        .unwrap();
+    let artifacts_dir = temp_dir.join("artifacts");
+    std::fs::create_dir_all(artifacts_dir.join("build/target/dev")).unwrap();
+
+    let trace = std::fs::read_to_string(temp_dir.join("trace_balance_simple.json")).unwrap();
+    let mut ci_trace = trace.clone();
+    for artifact in [
+        "balance_simple_integrationtest.test.sierra.json",
+        "balance_simple_integrationtest_HelloStarknet.test.contract_class.json",
+    ] {
+        ci_trace = ci_trace.replace(
+            &format!("\"{artifact}\""),
+            &format!("\"/ci/build/target/dev/{artifact}\""),
+        );
+        std::fs::copy(temp_dir.join(artifact), artifacts_dir.join(artifact)).unwrap();
+        std::fs::copy(
+            temp_dir.join(artifact),
+            artifacts_dir.join("build/target/dev").join(artifact),
+        )
+        .unwrap();
+    }
+    assert_ne!(trace, ci_trace);
+    std::fs::write(temp_dir.join("ci_trace.json"), ci_trace).unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("trace_balance_simple.json")
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("ci_trace.json")
+        .args(["--output-path", "ci.pb.gz"])
+        .args(args)
+        .assert()
+        .success();
+
+    let view = |profile: &str| {
+        SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("view")
+            .arg(profile)
+            .args(["--limit", "2137", "--sample", "sierra gas"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+
+    assert_eq!(
+        str::from_utf8(&view("profile.pb.gz")).unwrap(),
+        str::from_utf8(&view("ci.pb.gz")).unwrap()
+    );
+}
+
+#[test]
+fn trace_from_another_machine_without_remapping() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root.join(
+                "crates/cairo-profiler/tests/contracts/balance_simple/precompiled_sierra_gas/",
+            ),
+            &["*.json"],
+        )
+        .unwrap();
+
+    let trace = std::fs::read_to_string(temp_dir.join("trace_balance_simple.json"))
+        .unwrap()
+        .replace(
+            "\"balance_simple_integrationtest.test.sierra.json\"",
+            "\"/ci/build/target/dev/balance_simple_integrationtest.test.sierra.json\"",
+        );
+    std::fs::write(temp_dir.join("ci_trace.json"), trace).unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("ci_trace.json")
+        .assert()
+        .failure()
+        .stderr_eq(indoc!(
+            "
+            Error: Failed to canonicalize path: /ci/build/target/dev/balance_simple_integrationtest.test.sierra.json
+            If the trace was generated on another machine, use `--remap-path-prefix` or `--sierra-search-dir` to locate the artifacts
+            "
+        ));
+}
+
+#[test_case(false; "in_memory")]
+#[test_case(true; "stream")]
+fn build_profile_from_bundle(stream: bool) {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    let project_dir = temp_dir.join("project");
+    let other_dir = temp_dir.join("other");
+    std::fs::create_dir_all(&other_dir).unwrap();
+    assert_fs::fixture::PathChild::child(&temp_dir, "project")
+        .copy_from(
+            project_root.join(
+                "crates/cairo-profiler/tests/contracts/balance_simple/precompiled_sierra_gas/",
+            ),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&project_dir)
+        .arg("build-profile")
+        .arg("trace_balance_simple.json")
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&project_dir)
+        .arg("bundle")
+        .arg("trace_balance_simple.json")
+        .args(["--output-path", "../other/bundle.tar.gz"])
+        .assert()
+        .success();
+
+    let bundle = std::fs::File::open(other_dir.join("bundle.tar.gz")).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bundle));
+    let entries: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            "trace.json",
+            "sierra/balance_simple_integrationtest.test.sierra.json",
+            "sierra/balance_simple_integrationtest_HelloStarknet.test.contract_class.json",
+        ]
+    );
+
+    let mut command = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&other_dir)
+        .arg("build-profile")
+        .arg("bundle.tar.gz");
+    if stream {
+        command = command.arg("--stream");
+    }
+    command.assert().success();
+
+    let view = |dir: &std::path::Path| {
+        SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(dir)
+            .arg("view")
+            .arg("profile.pb.gz")
+            .args(["--limit", "2137", "--sample", "sierra gas"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+
+    assert_eq!(
+        str::from_utf8(&view(&project_dir)).unwrap(),
+        str::from_utf8(&view(&other_dir)).unwrap()
+    );
+}
+
+#[test]
+fn view_fee_estimation() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+    std::fs::write(
+        temp_dir.join("gas_prices.json"),
+        indoc!(
+            r#"
+            {
+              "block_number": 1234,
+              "l1_gas_price": { "price_in_fri": "0x2", "price_in_wei": "0x1" },
+              "l1_data_gas_price": { "price_in_fri": "0x5", "price_in_wei": "0x1" },
+              "l2_gas_price": { "price_in_fri": "0x3", "price_in_wei": "0x1" }
+            }
+            "#
+        ),
+    )
+    .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--gas-prices-path", "gas_prices.json"])
+        .assert()
+        .success();
+
+    // Fees are the l2 gas from `view_l2_gas_with_signature` and the l1 data gas from `view_l1_data_gas`
+    // multiplied by their prices
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "4", "--sample", "fee fri"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 2056280 fee fri, 65.08% of 3159460 fee fri total
+            Showing top 4 nodes out of 42
+            
+                       flat |  flat% |   sum% |            cum |   cum% |  
+            ----------------+--------+--------+----------------+--------+---------------------------------------------------------------
+             660000 fee fri | 20.89% | 20.89% | 660000 fee fri | 20.89% | "StorageRead" 
+             610800 fee fri | 19.33% | 40.22% | 610800 fee fri | 19.33% | "EmitEvent" 
+             576080 fee fri | 18.23% | 58.46% | 576080 fee fri | 18.23% | "StorageWrite" 
+             209400 fee fri |  6.63% | 65.08% | 975880 fee fri | 30.89% | "l2_verification::erc20::ERC20::StorageImpl::transfer_helper" 
+            "#
+        ));
+
+    // Explicit prices take precedence over the ones from the file
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--gas-prices-path", "gas_prices.json"])
+        .args(["--l2-gas-price", "7", "--fee-unit", "wei"])
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "1", "--sample", "fee wei"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 1540000 fee wei, 20.93% of 7358420 fee wei total
+            Showing top 1 nodes out of 42
+            
+                        flat |  flat% |   sum% |             cum |   cum% |  
+            -----------------+--------+--------+-----------------+--------+---------------
+             1540000 fee wei | 20.93% | 20.93% | 1540000 fee wei | 20.93% | "StorageRead" 
+            "#
+        ));
+}
+
+#[test]
+fn fee_estimation_missing_price() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--l2-gas-price", "0x3", "--l1-gas-price", "0X2"])
+        .assert()
+        .failure()
+        .stderr_eq(indoc!(
+            "
+            Error: Missing l1_data_gas price in fri. Provide it with `--l1-data-gas-price` or in the gas prices file
+            "
+        ));
+}
+
+#[test]
+fn view_l1_data_gas() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .assert()
+        .success();
+
+    // 19 storage writes, 2 felts each, and a single header of the modified ERC20 contract,
+    // charged to its constructor
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "9", "--sample", "l1 data gas"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 1280 l1 data gas, 100.00% of 1280 l1 data gas total
+            Showing top 9 nodes out of 42
+            
+                         flat |  flat% |    sum% |              cum |    cum% |  
+            ------------------+--------+---------+------------------+---------+-----------------------------------------------------------------------
+             1216 l1 data gas | 95.00% |  95.00% | 1216 l1 data gas |  95.00% | "StorageWrite" 
+               64 l1 data gas |  5.00% | 100.00% |  512 l1 data gas |  40.00% | "Contract: ERC20/nFunction: constructor/n" 
+                0 l1 data gas |  0.00% | 100.00% |  768 l1 data gas |  60.00% | "CallContract" 
+                0 l1 data gas |  0.00% | 100.00% |    0 l1 data gas |   0.00% | "Contract: ERC20/nFunction: allowance/n" 
+                0 l1 data gas |  0.00% | 100.00% |    0 l1 data gas |   0.00% | "Contract: ERC20/nFunction: balance_of/n" 
+                0 l1 data gas |  0.00% | 100.00% |  128 l1 data gas |  10.00% | "Contract: ERC20/nFunction: increase_allowance/n" 
+                0 l1 data gas |  0.00% | 100.00% |  256 l1 data gas |  20.00% | "Contract: ERC20/nFunction: transfer/n" 
+                0 l1 data gas |  0.00% | 100.00% |  384 l1 data gas |  30.00% | "Contract: ERC20/nFunction: transfer_from/n" 
+                0 l1 data gas |  0.00% | 100.00% | 1280 l1 data gas | 100.00% | "Contract: SNFORGE_TEST_CODE/nFunction: SNFORGE_TEST_CODE_FUNCTION/n" 
+            "#
+        ));
+}
+
+#[test]
+fn view_storage_variables() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--show-storage-variables")
+        .args(["--storage-variable", "balances"])
+        .args(["--storage-variable", "allowances"])
+        .args(["--storage-variable", "name"])
+        .args(["--storage-variable", "total_supply"])
+        .assert()
+        .success();
+
+    // Names of `Map`s are not found in the compiled artifacts, so they are given with the flag.
+    // Names not given are guessed and marked with `?`, given ones are shown as they are.
+    // Addresses reused after being accessed once cannot be recognised, so such accesses are unknown.
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "7", "--sample", "l1 data gas"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 1216 l1 data gas, 95.00% of 1280 l1 data gas total
+            Showing top 7 nodes out of 49
+            
+                        flat |  flat% |   sum% |             cum |   cum% |  
+            -----------------+--------+--------+-----------------+--------+--------------------------------------------
+             768 l1 data gas | 60.00% | 60.00% | 768 l1 data gas | 60.00% | "<unknown storage variable>" 
+             128 l1 data gas | 10.00% | 70.00% | 128 l1 data gas | 10.00% | "allowances" 
+              64 l1 data gas |  5.00% | 75.00% | 512 l1 data gas | 40.00% | "Contract: ERC20/nFunction: constructor/n" 
+              64 l1 data gas |  5.00% | 80.00% |  64 l1 data gas |  5.00% | "balances" 
+              64 l1 data gas |  5.00% | 85.00% |  64 l1 data gas |  5.00% | "decimals?" 
+              64 l1 data gas |  5.00% | 90.00% |  64 l1 data gas |  5.00% | "name" 
+              64 l1 data gas |  5.00% | 95.00% |  64 l1 data gas |  5.00% | "symbol?" 
+            "#
+        ));
+}
+
+#[test]
+fn view_storage_variables_from_compiled_artifacts() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--show-storage-variables")
+        .assert()
+        .success();
+
+    // Names are guessed from identifiers in the ABI, e.g. `get_name` getter or `name_` argument
+    // of the constructor, so they are marked with `?`. Variables accessed only through `Map`s are
+    // not recognised.
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "6", "--sample", "l1 data gas"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 1280 l1 data gas, 100.00% of 1280 l1 data gas total
+            Showing top 6 nodes out of 47
+            
+                        flat |  flat% |    sum% |             cum |   cum% |  
+            -----------------+--------+---------+-----------------+--------+--------------------------------------------
+             960 l1 data gas | 75.00% |  75.00% | 960 l1 data gas | 75.00% | "<unknown storage variable>" 
+              64 l1 data gas |  5.00% |  80.00% | 512 l1 data gas | 40.00% | "Contract: ERC20/nFunction: constructor/n" 
+              64 l1 data gas |  5.00% |  85.00% |  64 l1 data gas |  5.00% | "decimals?" 
+              64 l1 data gas |  5.00% |  90.00% |  64 l1 data gas |  5.00% | "name?" 
+              64 l1 data gas |  5.00% |  95.00% |  64 l1 data gas |  5.00% | "symbol?" 
+              64 l1 data gas |  5.00% | 100.00% |  64 l1 data gas |  5.00% | "total_supply?" 
+            "#
+        ));
+
+    // Variables which names are not found are labelled with their base addresses
+    let class_path =
+        temp_dir.join("l2_verification_integrationtest_ERC20.test.contract_class.json");
+    let mut class: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&class_path).unwrap()).unwrap();
+    class["abi"] = serde_json::Value::Null;
+    fs::write(&class_path, class.to_string()).unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--show-storage-variables")
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "6", "--sample", "l1 data gas"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 1280 l1 data gas, 100.00% of 1280 l1 data gas total
+            Showing top 6 nodes out of 47
+            
+                        flat |  flat% |    sum% |             cum |   cum% |  
+            -----------------+--------+---------+-----------------+--------+---------------------------------------------------------------------
+             960 l1 data gas | 75.00% |  75.00% | 960 l1 data gas | 75.00% | "<unknown storage variable>" 
+              64 l1 data gas |  5.00% |  80.00% |  64 l1 data gas |  5.00% | "0x1557182e4359a1f0c6301278e8f5b35a776ab58d39892581e357578fb287836" 
+              64 l1 data gas |  5.00% |  85.00% |  64 l1 data gas |  5.00% | "0x216b05c387bab9ac31918a3e61672f4618601f3c598a2f3f2710f37053e1ea4" 
+              64 l1 data gas |  5.00% |  90.00% |  64 l1 data gas |  5.00% | "0x361458367e696363fbcc70777d07ebbd2394e89fd0adcaf147faccd1d294d60" 
+              64 l1 data gas |  5.00% |  95.00% |  64 l1 data gas |  5.00% | "0x4c4fb1ab068f6039d5780c68dd0fa2f8742cceb3426d19667778ca7f3518a9" 
+              64 l1 data gas |  5.00% | 100.00% | 512 l1 data gas | 40.00% | "Contract: ERC20/nFunction: constructor/n" 
+            "#
+        ));
+}
+
+#[test]
+fn compare_starknet_versions() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--compare-starknet-version", "0.13.4"])
+        .assert()
+        .success();
+
+    for (version, total, percent) in [
+        ("0.13.4", "1043100", "21.09%"),
+        ("0.14.1", "1051020", "20.93%"),
+    ] {
+        SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("view")
+            .arg("profile.pb.gz")
+            .args(["--limit", "1", "--sample", &format!("l2 gas@{version}")])
+            .assert()
+            .success()
+            .stdout_eq(formatdoc!(
+                r#"
+                
+                Showing nodes accounting for 220000 l2 gas@{version}, {percent} of {total} l2 gas@{version} total
+                Showing top 1 nodes out of 42
+                
+                                 flat |  flat% |   sum% |                  cum |   cum% |  
+                ----------------------+--------+--------+----------------------+--------+---------------
+                 220000 l2 gas@{version} | {percent} | {percent} | 220000 l2 gas@{version} | {percent} | "StorageRead" 
+                "#
+            ));
+    }
+
+    // Measurements which do not depend on versioned constants are saved once
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "1", "--sample", "calls"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 3 calls, 30.00% of 10 calls total
+            Showing top 1 nodes out of 42
+            
+                flat |  flat% |   sum% |     cum |   cum% |  
+            ---------+--------+--------+---------+--------+-------------------------------------------
+             3 calls | 30.00% | 30.00% | 3 calls | 30.00% | "Contract: ERC20/nFunction: balance_of/n" 
+            "#
+        ));
+
+    // Function calls are not priced either, so calls of functions are still found by `--per-call`
+    let output = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args([
+            "--limit",
+            "4",
+            "--sample",
+            "sierra gas@0.13.4",
+            "--per-call",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output_str = str::from_utf8(&output).expect("Output was not valid utf-8");
+    assert!(
+        output_str.contains(
+            r#"|     2 | 34900.00 sierra gas@0.13.4 | 131500.00 sierra gas@0.13.4 | "l2_verification::erc20::ERC20::StorageImpl::transfer_helper""#
+        ),
+        "Output contains: {output_str}, missing calls of transfer_helper",
+    );
+}
+
+#[test]
+fn compare_starknet_versions_stream() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // Streamed traces are priced under both versioned constants the same way
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--stream")
+        .args(["--compare-starknet-version", "0.13.4"])
+        .assert()
+        .success();
+
+    for (version, total, percent) in [
+        ("0.13.4", "1043100", "21.09%"),
+        ("0.14.1", "1051020", "20.93%"),
+    ] {
+        SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("view")
+            .arg("profile.pb.gz")
+            .args(["--limit", "1", "--sample", &format!("l2 gas@{version}")])
+            .assert()
+            .success()
+            .stdout_eq(formatdoc!(
+                r#"
+                
+                Showing nodes accounting for 220000 l2 gas@{version}, {percent} of {total} l2 gas@{version} total
+                Showing top 1 nodes out of 42
+                
+                                 flat |  flat% |   sum% |                  cum |   cum% |  
+                ----------------------+--------+--------+----------------------+--------+---------------
+                 220000 l2 gas@{version} | {percent} | {percent} | 220000 l2 gas@{version} | {percent} | "StorageRead" 
+                "#
+            ));
+    }
+}
+
+#[test]
+fn compare_starknet_versions_syscall_builtins() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_cairo_steps/"),
+            &["*.json"],
+        )
+        .unwrap();
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--compare-starknet-version", "0.13.4"])
+        .assert()
+        .success()
+        .stderr_eq("");
+
+    // Builtins used by syscalls are taken from versioned constants, so they are saved for both
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "1", "--sample", "range check builtin@0.13.4"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 120 range check builtin@0.13.4, 32.61% of 368 range check builtin@0.13.4 total
+            Showing top 1 nodes out of 42
+
+                                       flat |  flat% |   sum% |                            cum |   cum% |  
+            --------------------------------+--------+--------+--------------------------------+--------+----------------
+             120 range check builtin@0.13.4 | 32.61% | 32.61% | 301 range check builtin@0.13.4 | 81.79% | "CallContract" 
+            "#
+        ));
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "1", "--sample", "range check builtin@0.14.1"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 144 range check builtin@0.14.1, 36.46% of 395 range check builtin@0.14.1 total
+            Showing top 1 nodes out of 42
+
+                                       flat |  flat% |   sum% |                            cum |   cum% |  
+            --------------------------------+--------+--------+--------------------------------+--------+----------------
+             144 range check builtin@0.14.1 | 36.46% | 36.46% | 328 range check builtin@0.14.1 | 83.04% | "CallContract" 
+            "#
+        ));
+
+    // Memory holes of syscalls are the same in both versioned constants, so they are saved once
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "1", "--sample", "memory holes"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn compare_same_starknet_versions() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--compare-starknet-version", "0.14.1"])
+        .env("RUST_BACKTRACE", "0")
+        .assert()
+        .failure()
+        .stderr_eq(indoc!(
+            r"
+            Error: Compared versioned constants must differ from the ones the profile is built with, both are 0.14.1
+            "
+        ));
+}
+
+#[test]
+fn override_costs() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // Overridden constants can be compared with the ones they were read from
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--override-cost", "syscall:StorageRead.n_steps=190"])
+        .args(["--override-cost", "builtin:poseidon=1000"])
+        .args(["--compare-starknet-version", "0.14.1"])
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "1", "--sample", "l2 gas@0.14.1+overrides"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 419540 l2 gas@0.14.1+overrides, 33.55% of 1250560 l2 gas@0.14.1+overrides total
+            Showing top 1 nodes out of 42
+
+                                       flat |  flat% |   sum% |                            cum |   cum% |  
+            --------------------------------+--------+--------+--------------------------------+--------+---------------
+             419540 l2 gas@0.14.1+overrides | 33.55% | 33.55% | 419540 l2 gas@0.14.1+overrides | 33.55% | "StorageRead" 
+            "#
+        ));
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--override-cost", "syscall:StorageRead.calldata_factor.n_steps=1"])
+        .env("RUST_BACKTRACE", "0")
+        .assert()
+        .failure()
+        .stderr_eq(indoc!(
+            r"
+            Error: Cannot apply cost override `syscall:StorageRead.calldata_factor.n_steps=1`: StorageRead syscall is not scaled with calldata
+            "
+        ));
+}
+
+#[test]
+fn view_info() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--split-generics")
+        .arg("--show-libfuncs")
+        .args(["--override-cost", "builtin:poseidon=1000"])
+        .args(["--compare-starknet-version", "0.13.4"])
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .arg("--info")
+        .assert()
+        .success()
+        .stdout_eq(formatdoc!(
+            r"
+            cairo-profiler version: {}
+            trace: l2_verification_integrationtest_test_l2_with_signature.json
+            external tool: snforge
+            versioned constants: Starknet 0.14.1
+            compared versioned constants: Starknet 0.13.4
+            cost override: builtin:poseidon=1000
+            sierra gas tracking: enabled
+            enabled flags: --split-generics --show-libfuncs
+            max function stack trace depth: 100
+            default sample: sierra gas@0.14.1+overrides
+            ",
+            env!("CARGO_PKG_VERSION")
+        ));
+
+    temp_dir
+        .copy_from(
+            project_root.join(
+                "crates/cairo-profiler/tests/contracts/balance_simple/precompiled_cairo_steps/",
+            ),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("trace_balance_simple.json")
+        .assert()
+        .success();
+
+    // Steps are the default sample of traces without sierra gas
+    let output = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .arg("--info")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output_str = str::from_utf8(&output).expect("Output was not valid utf-8");
+
+    assert!(
+        output_str.contains("sierra gas tracking: disabled\nenabled flags: none\n"),
+        "Output contains: {output_str}, missing disabled sierra gas tracking",
+    );
+    assert!(
+        output_str.contains("default sample: steps\n"),
+        "Output contains: {output_str}, missing 'default sample: steps'",
+    );
+}
+
+#[test]
+fn reproducible_profile_output() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // Streaming yields the same profile as reading the whole trace
+    for (output_path, stream) in [
+        ("first.pb.gz", false),
+        ("second.pb.gz", false),
+        ("streamed.pb.gz", true),
+    ] {
+        let mut command = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("build-profile")
+            .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+            .args(["--output-path", output_path])
+            .arg("--show-libfuncs")
+            .env_remove("SOURCE_DATE_EPOCH");
+        if stream {
+            command = command.arg("--stream");
+        }
+        command.assert().success();
+    }
+
+    let first = fs::read(temp_dir.join("first.pb.gz")).unwrap();
+    assert_eq!(first, fs::read(temp_dir.join("second.pb.gz")).unwrap());
+    assert_eq!(first, fs::read(temp_dir.join("streamed.pb.gz")).unwrap());
+
+    // The build time is recorded only if given explicitly
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("first.pb.gz")
+        .arg("--info")
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r"
+            ...
+            default sample: sierra gas
+            "
+        ));
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--output-path", "dated.pb.gz"])
+        .env("SOURCE_DATE_EPOCH", "1700000000")
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("dated.pb.gz")
+        .arg("--info")
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r"
+            ...
+            built at: 1700000000 (seconds since the Unix epoch)
+            "
+        ));
+}
+
+#[test]
+fn sample_aggregation() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    for (output_path, no_sample_aggregation) in
+        [("aggregated.pb.gz", false), ("per_call.pb.gz", true)]
+    {
+        let mut command = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("build-profile")
+            .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+            .args(["--output-path", output_path])
+            .arg("--show-libfuncs");
+        if no_sample_aggregation {
+            command = command.arg("--no-sample-aggregation");
+        }
+        command.assert().success();
+    }
+
+    let aggregated_size = fs::metadata(temp_dir.join("aggregated.pb.gz"))
+        .unwrap()
+        .len();
+    let per_call_size = fs::metadata(temp_dir.join("per_call.pb.gz")).unwrap().len();
+    assert!(
+        aggregated_size < per_call_size,
+        "Aggregated profile ({aggregated_size} bytes) is not smaller than the per call one ({per_call_size} bytes)",
+    );
+
+    // Aggregation does not change the totals
+    for sample in ["calls", "sierra gas", "l2 gas"] {
+        let view = |profile: &str| {
+            SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+                .current_dir(&temp_dir)
+                .arg("view")
+                .arg(profile)
+                .args(["--sample", sample, "--limit", "100"])
+                .assert()
+                .success()
+                .get_output()
+                .stdout
+                .clone()
+        };
+        assert_eq!(view("aggregated.pb.gz"), view("per_call.pb.gz"));
+    }
+}
+
+#[test]
+fn split_entrypoint_invocations() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--split-entrypoint-invocations")
+        .assert()
+        .success();
+
+    let output = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--sample", "sierra gas", "--limit", "100"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output_str = str::from_utf8(&output).expect("Output was not valid utf-8");
+
+    // `balance_of` is called 3 times, each invocation costing the same
+    for invocation in 1..=3 {
+        let expected_row = format!(
+            r#" 33150 sierra gas |   1.80% | "Contract: ERC20\nFunction: balance_of\nInvocation: {invocation}\n""#
+        );
+        assert!(
+            output_str.contains(&expected_row),
+            "Output contains: {output_str}, missing invocation {invocation} of balance_of",
+        );
+    }
+    assert!(
+        !output_str.contains(r"Function: balance_of\nInvocation: 4"),
+        "Output contains: {output_str}, with too many invocations of balance_of",
+    );
+}
+
+#[test]
+fn truncated_call_stacks() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--max-function-stack-trace-depth", "2"])
+        .assert()
+        .success()
+        .stderr_eq(indoc!(
+            r"
+            [WARNING] Some function call stacks were deeper than 2 functions and were truncated - costs of the deepest functions are attributed to their callers. Consider increasing `--max-function-stack-trace-depth`.
+            "
+        ));
+
+    // Syscalls called from truncated stacks are kept
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--sample", "sierra gas", "--limit", "1"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 732480 sierra gas, 39.74% of 1843100 sierra gas total
+            Showing top 1 nodes out of 25
+
+                          flat |  flat% |   sum% |                cum |   cum% |  
+            -------------------+--------+--------+--------------------+--------+----------------
+             732480 sierra gas | 39.74% | 39.74% | 1397850 sierra gas | 75.84% | "CallContract" 
+            "#
+        ));
+}
+
+#[test]
+fn show_loops() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root.join("crates/cairo-profiler/tests/contracts/scaled_syscall/precompiled/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("scaled_syscall_deploy_syscall_cost_but_different.json")
+        .arg("--show-loops")
+        .assert()
+        .success();
+
+    // The program was compiled without statements code locations, so the compiler suffix is kept
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--sample", "sierra gas", "--limit", "2137"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            ...
+              18000 sierra gas |  6.17% |  83.49% |  18000 sierra gas |   6.17% | "core::keccak::keccak_u256s_le_inputs::loop[637-804]" 
+            ...
+            "#
+        ));
+
+    // Add code locations of statements of the loop function (statements 290-428 of the class).
+    // The second statement comes from an inlined function, so its innermost location is skipped.
+    let class_path = temp_dir
+        .join("scaled_syscall_unittest_GasConstructorCheckerButDifferent.test.contract_class.json");
+    let mut class: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&class_path).unwrap()).unwrap();
+    let location = |line: usize| {
+        serde_json::json!([
+            "/path/to/lib.cairo",
+            { "start": { "line": line, "col": 8 }, "end": { "line": line, "col": 8 } }
+        ])
+    };
+    class["sierra_program_debug_info"]["annotations"]["github.com/software-mansion/cairo-coverage"] = serde_json::json!({
+        "statements_code_locations": {
+            "300": [location(45)],
+            "320": [location(3), location(41)],
+        }
+    });
+    fs::write(&class_path, class.to_string()).unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("scaled_syscall_deploy_syscall_cost_but_different.json")
+        .arg("--show-loops")
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--sample", "sierra gas", "--limit", "2137"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            ...
+              18000 sierra gas |  6.17% |  83.49% |  18000 sierra gas |   6.17% | "core::keccak::keccak_u256s_le_inputs::loop@line 42" 
+            ...
+            "#
+        ));
+}
+
+#[test]
+fn libfunc_report() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--libfunc-report")
+        .args(["--libfunc-report-path", "reports/libfuncs.json"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Libfunc summary (35 libfuncs)
+
+             calls | sierra gas | builtins sierra gas | libfunc                           | top callers 
+            -------+------------+---------------------+-----------------------------------+----------[..]
+              1142 |     320100 |                   0 | store_temp                        | "l2_verification_integrationtest::test_l2::with_signature_return_wrapper", [..]
+                24 |     102000 |               97200 | pedersen                          | "l2_verification::erc20::ERC20::StorageImpl::transfer_helper", [..]
+            ...
+            "#
+        ));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp_dir.join("reports/libfuncs.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        report[1],
+        serde_json::json!({
+            "libfunc": "pedersen",
+            "calls": 24,
+            "steps": 0,
+            "sierra_gas": 102_000,
+            "builtins_sierra_gas": 97_200,
+            "top_callers": [
+                {
+                    "function": "l2_verification::erc20::ERC20::StorageImpl::transfer_helper",
+                    "calls": 8,
+                    "steps": 0,
+                    "sierra_gas": 34_000,
+                    "builtins_sierra_gas": 32_400
+                },
+                {
+                    "function": "l2_verification::erc20::ERC20::StorageImpl::approve_helper",
+                    "calls": 4,
+                    "steps": 0,
+                    "sierra_gas": 17_000,
+                    "builtins_sierra_gas": 16_200
+                },
+                {
+                    "function": "l2_verification::erc20::ERC20::__wrapper__IERC20Impl__allowance",
+                    "calls": 4,
+                    "steps": 0,
+                    "sierra_gas": 17_000,
+                    "builtins_sierra_gas": 16_200
+                }
+            ]
+        })
+    );
+}
+
+#[test]
+fn libfunc_report_steps() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_cairo_steps/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // Builtins are not charged in steps, so they are reported as their counts
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--libfunc-report")
+        .args(["--libfunc-report-path", "reports/libfuncs.json"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Libfunc summary (35 libfuncs)
+
+             calls | steps | builtins               | libfunc                           | top callers 
+            -------+-------+------------------------+-----------------------------------+----------[..]
+              1142 |  3201 |                        | store_temp                        | "l2_verification_integrationtest::test_l2::with_signature_return_wrapper", [..]
+            ...
+                41 |    82 | 41 range_check_builtin | u128s_from_felt252                | "core::starknet::storage::MutableStorableStoragePointer0OffsetReadAccess::read", [..]
+            ...
+                24 |    48 | 24 pedersen_builtin    | pedersen                          | "l2_verification::erc20::ERC20::StorageImpl::transfer_helper", [..]
+            ...
+            "#
+        ));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp_dir.join("reports/libfuncs.json")).unwrap())
+            .unwrap();
+    assert_eq!(report[14]["libfunc"], "pedersen");
+    assert_eq!(
+        report[14]["builtins"],
+        serde_json::json!({ "pedersen_builtin": 24 })
+    );
+    assert_eq!(
+        report[14]["top_callers"][0],
+        serde_json::json!({
+            "function": "l2_verification::erc20::ERC20::StorageImpl::transfer_helper",
+            "calls": 8,
+            "steps": 16,
+            "sierra_gas": 0,
+            "builtins_sierra_gas": 0,
+            "builtins": { "pedersen_builtin": 8 }
+        })
+    );
+}
+
+#[test]
+fn function_level_builtins_and_memory_holes() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_cairo_steps/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .assert()
+        .success()
+        .stderr_eq("");
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .arg("--sample")
+        .arg("pedersen builtin")
+        .arg("--limit")
+        .arg("3")
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 16 pedersen builtin, 66.67% of 24 pedersen builtin total
+            Showing top 3 nodes out of 42
+
+                           flat |  flat% |   sum% |                cum |   cum% |  
+            --------------------+--------+--------+--------------------+--------+-------------------------------------------------------------------
+             8 pedersen builtin | 33.33% | 33.33% | 8 pedersen builtin | 33.33% | "l2_verification::erc20::ERC20::StorageImpl::transfer_helper" 
+             4 pedersen builtin | 16.67% | 50.00% | 4 pedersen builtin | 16.67% | "l2_verification::erc20::ERC20::StorageImpl::approve_helper" 
+             4 pedersen builtin | 16.67% | 66.67% | 4 pedersen builtin | 16.67% | "l2_verification::erc20::ERC20::__wrapper__IERC20Impl__allowance" 
+            "#
+        ));
+
+    // Holes not explained by functions are left to the entrypoints, none of them is negative
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .arg("--sample")
+        .arg("memory holes")
+        .arg("--limit")
+        .arg("3")
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 272 memory holes, 59.52% of 457 memory holes total
+            Showing top 3 nodes out of 42
+
+                        flat |  flat% |   sum% |             cum |   cum% |  
+            -----------------+--------+--------+-----------------+--------+---------------------------------------------------------------------------------
+             96 memory holes | 21.01% | 21.01% | 96 memory holes | 21.01% | "core::starknet::storage::MutableStorableStoragePointer0OffsetReadAccess::read" 
+             96 memory holes | 21.01% | 42.01% | 96 memory holes | 21.01% | "snforge_std::cheatcodes::execution_info::TxInfoMockSerde::serialize" 
+             80 memory holes | 17.51% | 59.52% | 80 memory holes | 17.51% | "core::starknet::storage::StorableStoragePointer0OffsetReadAccess::read" 
+            "#
+        ));
+}
+
+#[test]
+fn function_level_vm_resources_match_trace() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_cairo_steps/"),
+            &["*.json"],
+        )
+        .unwrap();
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .assert()
+        .success();
+
+    let trace: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(
+            temp_dir.join("l2_verification_integrationtest_test_l2_with_signature.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let vm_resources = &trace["used_execution_resources"]["vm_resources"];
+    for (sample, total) in [
+        ("memory holes", &vm_resources["n_memory_holes"]),
+        (
+            "pedersen builtin",
+            &vm_resources["builtin_instance_counter"]["pedersen_builtin"],
+        ),
+    ] {
+        let output = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("view")
+            .arg("profile.pb.gz")
+            .args(["--sample", sample])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let output_str = str::from_utf8(&output).expect("Output was not valid utf-8");
+        assert!(
+            output_str.contains(&format!("of {total} {sample} total")),
+            "Output contains: {output_str}, with {sample} total different than {total} reported in the trace",
+        );
+        assert!(
+            !output_str.contains(" -"),
+            "Output contains: {output_str}, with negative {sample}",
+        );
+    }
+
+    // Traces tracking sierra gas do not report builtins and memory holes, but they are still
+    // counted for functions
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .assert()
+        .success();
+    for (sample, function) in [
+        (
+            "range check builtin",
+            "core::starknet::storage::MutableStorableStoragePointer0OffsetReadAccess::read",
+        ),
+        (
+            "pedersen builtin",
+            "l2_verification::erc20::ERC20::StorageImpl::transfer_helper",
+        ),
+        (
+            "memory holes",
+            "core::starknet::storage::MutableStorableStoragePointer0OffsetReadAccess::read",
+        ),
+    ] {
+        let output = SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+            .current_dir(&temp_dir)
+            .arg("view")
+            .arg("profile.pb.gz")
+            .args(["--sample", sample])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let output_str = str::from_utf8(&output).expect("Output was not valid utf-8");
+        let line = output_str
+            .lines()
+            .find(|line| line.contains(&format!("\"{function}\"")))
+            .unwrap_or_else(|| panic!("Output contains: {output_str}, without {function}"));
+        assert!(
+            !line.trim_start().starts_with(&format!("0 {sample}")),
+            "Output contains: {output_str}, with no {sample} in {function}",
+        );
+    }
+}
+
+#[test]
+fn view_function_calls() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .arg("--sample")
+        .arg("function calls")
+        .arg("--limit")
+        .arg("3")
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 21 function calls, 26.58% of 79 function calls total
+            Showing top 3 nodes out of 42
+
+                          flat |  flat% |   sum% |               cum |   cum% |  
+            -------------------+--------+--------+-------------------+--------+---------------------------------------------------------------------------------
+             10 function calls | 12.66% | 12.66% | 10 function calls | 12.66% | "core::array::serialize_array_helper" 
+              6 function calls |  7.59% | 20.25% |  6 function calls |  7.59% | "core::starknet::storage::MutableStorableStoragePointer0OffsetReadAccess::read" 
+              5 function calls |  6.33% | 26.58% |  5 function calls |  6.33% | "core::starknet::storage::StorableStoragePointer0OffsetReadAccess::read" 
+            "#
+        ));
+}
+
+#[test]
+fn view_per_call() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .assert()
+        .success();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .arg("--sample")
+        .arg("sierra gas")
+        .arg("--limit")
+        .arg("4")
+        .arg("--per-call")
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+
+            Showing nodes accounting for 1212280 sierra gas, 65.77% of 1843100 sierra gas total
+            Showing top 4 nodes out of 42
+
+                          flat |  flat% |   sum% |                cum |   cum% | calls |           flat/call |             cum/call |  
+            -------------------+--------+--------+--------------------+--------+-------+---------------------+----------------------+---------------------------------------------------------------
+             732480 sierra gas | 39.74% | 39.74% | 1397850 sierra gas | 75.84% |     8 | 91560.00 sierra gas | 174731.25 sierra gas | "CallContract" 
+             220000 sierra gas | 11.94% | 51.68% |  220000 sierra gas | 11.94% |    22 | 10000.00 sierra gas |  10000.00 sierra gas | "StorageRead" 
+             190000 sierra gas | 10.31% | 61.99% |  190000 sierra gas | 10.31% |    19 | 10000.00 sierra gas |  10000.00 sierra gas | "StorageWrite" 
+              69800 sierra gas |  3.79% | 65.77% |  263000 sierra gas | 14.27% |     2 | 34900.00 sierra gas | 131500.00 sierra gas | "l2_verification::erc20::ERC20::StorageImpl::transfer_helper" 
+            "#
+        ));
+}
+
+#[test]
+fn view_transaction_costs() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .arg("--deploy-account-costs")
+        .arg("--declare-costs")
+        .assert()
+        .success();
+
+    // ERC20 class is declared once, even though it is executed by multiple transactions.
+    // Its constructor is called with 7 felts of calldata.
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "7", "--sample", "l2 gas"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 271001000 l2 gas, 99.88% of 271330700 l2 gas total
+            Showing top 7 nodes out of 44
+            
+                         flat |  flat% |   sum% |              cum |   cum% |  
+            ------------------+--------+--------+------------------+--------+---------------------------------------------------------------
+             270243840 l2 gas | 99.60% | 99.60% | 270243840 l2 gas | 99.60% | "DECLARE ERC20" 
+                220000 l2 gas |  0.08% | 99.68% |    220000 l2 gas |  0.08% | "StorageRead" 
+                203600 l2 gas |  0.08% | 99.76% |    203600 l2 gas |  0.08% | "EmitEvent" 
+                190000 l2 gas |  0.07% | 99.83% |    190000 l2 gas |  0.07% | "StorageWrite" 
+                 69800 l2 gas |  0.03% | 99.85% |    324440 l2 gas |  0.12% | "l2_verification::erc20::ERC20::StorageImpl::transfer_helper" 
+                 37920 l2 gas |  0.01% | 99.87% |     37920 l2 gas |  0.01% | "GetExecutionInfo" 
+                 35840 l2 gas |  0.01% | 99.88% |     35840 l2 gas |  0.01% | "DEPLOY_ACCOUNT calldata" 
+            "#
+        ));
+}
+
+#[test]
+fn view_l2_l1_messages() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/tree_verification/precompiled/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("mega_package_integrationtest_test_calls_test_call.json")
+        .assert()
+        .success();
+
+    // 11 messages with 2 felts of payload each, attributed to the syscalls sending them
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "2", "--sample", "l2 l1 message sizes"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 22 l2 l1 message sizes, 100.00% of 22 l2 l1 message sizes total
+            Showing top 2 nodes out of 38
+            
+                               flat |   flat% |    sum% |                    cum |    cum% |  
+            ------------------------+---------+---------+------------------------+---------+-------------------
+             22 l2 l1 message sizes | 100.00% | 100.00% | 22 l2 l1 message sizes | 100.00% | "SendMessageToL1" 
+              0 l2 l1 message sizes |   0.00% | 100.00% | 18 l2 l1 message sizes |  81.82% | "CallContract" 
+            "#
+        ));
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("view")
+        .arg("profile.pb.gz")
+        .args(["--limit", "3", "--sample", "l1 gas"])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r#"
+            
+            Showing nodes accounting for 61424 l1 gas, 100.00% of 61424 l1 gas total
+            Showing top 3 nodes out of 38
+            
+                     flat |   flat% |    sum% |          cum |    cum% |  
+            --------------+---------+---------+--------------+---------+-----------------------------------------------------------------------
+             61424 l1 gas | 100.00% | 100.00% | 61424 l1 gas | 100.00% | "SendMessageToL1" 
+                 0 l1 gas |   0.00% | 100.00% | 50256 l1 gas |  81.82% | "CallContract" 
+                 0 l1 gas |   0.00% | 100.00% | 61424 l1 gas | 100.00% | "Contract: SNFORGE_TEST_CODE/nFunction: SNFORGE_TEST_CODE_FUNCTION/n" 
+            "#
+        ));
+}
+
+#[test]
+fn disassemble_function() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // The function is also present in the test program, but it is executed only in the contract
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args([
+            "--disassemble",
+            "l2_verification::erc20::ERC20::StorageImpl::transfer_helper",
+        ])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r"
+
+            Disassembly of l2_verification::erc20::ERC20::StorageImpl::transfer_helper from [..]l2_verification_integrationtest_ERC20.test.contract_class.json
+
+             executions | offset | code 
+            ...
+                      2 |        | 1918: felt252_is_zero([9]) { fallthrough() 1932([10]) } 
+                      2 |   2749 |     jmp rel 15 if [fp + -6] != 0 
+                      0 |        | 1919: branch_align() -> () 
+                      0 |   2751 |     ap += 172 
+            ...
+                      2 |        | 1961: pedersen([2], [25], [19]) -> ([26], [27]) 
+                      2 |   2781 |     [ap + -1] = [[fp + -8] + 0] 
+                      2 |   2782 |     [fp + -6] = [[fp + -8] + 1] 
+            ...
+            "
+        ));
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("build-profile")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--disassemble", "non_existent_function"])
+        .assert()
+        .success()
+        .stdout_eq("")
+        .stderr_eq(indoc!(
+            "
+            [WARNING] Function non_existent_function was not executed in the trace
+            "
+        ));
+}
+
+#[test]
+fn annotate_function() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root
+                .join("crates/cairo-profiler/tests/contracts/l2_gas/precompiled_sierra_gas/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // Sierra gas of `pedersen` includes the cost of the builtin
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("annotate")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args([
+            "--function",
+            "l2_verification::erc20::ERC20::StorageImpl::transfer_helper",
+        ])
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r"
+
+            Annotation of l2_verification::erc20::ERC20::StorageImpl::transfer_helper from [..]l2_verification_integrationtest_ERC20.test.contract_class.json
+
+             executions | steps | sierra gas | statement 
+            ...
+                        |       |            | l2_verification::erc20::ERC20::StorageImpl::transfer_helper: 
+                      2 |     0 |          0 |     1916: dup<ContractAddress>([5]) -> ([5], [8]) 
+                      2 |     0 |          0 |     1917: contract_address_to_felt252([8]) -> ([9]) 
+                      2 |     2 |        200 |     1918: felt252_is_zero([9]) { fallthrough() 1932([10]) } 
+            ...
+                      2 |     2 |        200 |     1960: store_temp<felt252>([25]) -> ([25]) 
+                      2 |     4 |       8500 |     1961: pedersen([2], [25], [19]) -> ([26], [27]) 
+            ...
+                      2 |    20 |       2000 |     1973: storage_base_address_from_felt252([0], [37]) -> ([38], [39]) 
+            ...
+            "
+        ));
+
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("annotate")
+        .arg("l2_verification_integrationtest_test_l2_with_signature.json")
+        .args(["--function", "non_existent_function"])
+        .assert()
+        .success()
+        .stdout_eq("")
+        .stderr_eq(indoc!(
+            "
+            [WARNING] Function non_existent_function was not executed in the trace
+            "
+        ));
+}
+
+#[test]
+fn annotate_statements_without_casm() {
+    let project_root = project_root::get_project_root().unwrap();
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    temp_dir
+        .copy_from(
+            project_root.join("crates/cairo-profiler/tests/contracts/scaled_syscall/precompiled/"),
+            &["*.json"],
+        )
+        .unwrap();
+
+    // Statements compiling to no CASM and followed by a jump target cannot be counted
+    SnapboxCommand::new(cargo_bin!("cairo-profiler"))
+        .current_dir(&temp_dir)
+        .arg("annotate")
+        .arg("scaled_syscall_test_increase_balance.json")
+        .assert()
+        .success()
+        .stdout_eq(indoc!(
+            r"
+
+            Annotation of [..]scaled_syscall_unittest.test.sierra.json
+
+            ...
+                      - |     - |          - |     1738: [165]([72]) -> ([67]) 
+                      - |     - |          - |     1739: [144]([73]) -> ([68]) 
+                      - |     - |          - |     1740: [166]([74]) -> ([69]) 
+            ...
+            - statement compiles to no CASM instructions, so its executions are not known
+            ...
+            "
+        ));
+}